@@ -0,0 +1,246 @@
+use std::ops::{Add, Mul, Sub};
+use std::path::Path;
+
+use crate::camera::Camera;
+use crate::hittable::{HitRecord, Hittable};
+use crate::instance::Transform;
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
+
+pub trait Animatable:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f64, Output = Self>
+{
+}
+
+impl<T> Animatable for T where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f64, Output = T> {}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    CatmullRom,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Track<T> {
+    keys: Vec<(f64, T)>,
+    interpolation: Interpolation,
+}
+
+impl<T: Animatable> Track<T> {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            keys: Vec::new(),
+            interpolation,
+        }
+    }
+
+    pub fn key(mut self, time: f64, value: T) -> Self {
+        self.add_key(time, value);
+        self
+    }
+
+    pub fn add_key(&mut self, time: f64, value: T) {
+        let index = self.keys.partition_point(|&(t, _)| t <= time);
+        self.keys.insert(index, (time, value));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn sample(&self, time: f64) -> Option<T> {
+        let (first, last) = (self.keys.first()?, self.keys.last()?);
+        if time <= first.0 {
+            return Some(first.1);
+        }
+        if time >= last.0 {
+            return Some(last.1);
+        }
+
+        let i = self.keys.partition_point(|&(t, _)| t <= time) - 1;
+        let (t1, p1) = self.keys[i];
+        let (t2, p2) = self.keys[i + 1];
+        let s = (time - t1) / (t2 - t1);
+
+        match self.interpolation {
+            Interpolation::Linear => Some(p1 + (p2 - p1) * s),
+            Interpolation::CatmullRom => {
+                let (t0, p0) = if i > 0 { self.keys[i - 1] } else { (t1, p1) };
+                let (t3, p3) = self.keys.get(i + 2).copied().unwrap_or((t2, p2));
+
+                // Tangents are scaled to the segment so uneven key spacing
+                // doesn't overshoot.
+                let m1 = if t2 > t0 {
+                    (p2 - p0) * ((t2 - t1) / (t2 - t0))
+                } else {
+                    p2 - p1
+                };
+                let m2 = if t3 > t1 {
+                    (p3 - p1) * ((t2 - t1) / (t3 - t1))
+                } else {
+                    p2 - p1
+                };
+
+                let s2 = s * s;
+                let s3 = s2 * s;
+                Some(
+                    p1 * (2.0 * s3 - 3.0 * s2 + 1.0)
+                        + m1 * (s3 - 2.0 * s2 + s)
+                        + p2 * (-2.0 * s3 + 3.0 * s2)
+                        + m2 * (s3 - s2),
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CameraAnimation {
+    pub lookfrom: Track<Point3>,
+    pub lookat: Track<Point3>,
+    pub vfov: Track<f64>,
+    pub focus_distance: Track<f64>,
+    pub defocus_angle: Track<f64>,
+}
+
+impl CameraAnimation {
+    pub fn apply(&self, camera: &mut Camera, time: f64) {
+        if let Some(lookfrom) = self.lookfrom.sample(time) {
+            camera.lookfrom = lookfrom;
+        }
+        if let Some(lookat) = self.lookat.sample(time) {
+            camera.lookat = lookat;
+        }
+        if let Some(vfov) = self.vfov.sample(time) {
+            camera.vfov = vfov;
+        }
+        if let Some(focus_distance) = self.focus_distance.sample(time) {
+            camera.focus_distance = focus_distance;
+        }
+        if let Some(defocus_angle) = self.defocus_angle.sample(time) {
+            camera.defocus_angle = defocus_angle;
+        }
+    }
+}
+
+pub struct Animated {
+    object: Box<dyn Hittable>,
+    pub translation: Track<Vec3>,
+    pub rotation: Track<Vec3>,
+    pub scale: Track<f64>,
+}
+
+impl Animated {
+    pub fn new(object: Box<dyn Hittable>) -> Self {
+        Self {
+            object,
+            translation: Track::default(),
+            rotation: Track::default(),
+            scale: Track::default(),
+        }
+    }
+}
+
+impl Animated {
+    fn transform(&self, time: f64) -> Transform {
+        Transform::new(
+            self.translation.sample(time).unwrap_or_default(),
+            self.rotation.sample(time).unwrap_or_default(),
+            self.scale.sample(time).unwrap_or(1.0),
+        )
+    }
+}
+
+impl Hittable for Animated {
+    // The transform is evaluated at the ray's time, so animated objects also
+    // pick up motion blur within a frame.
+    fn hit(&self, r: &Ray, interval: Interval) -> Option<HitRecord> {
+        self.transform(r.time())
+            .hit(self.object.as_ref(), r, interval)
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        self.transform(r.time()).pdf_value(self.object.as_ref(), r)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        self.transform(time)
+            .random(self.object.as_ref(), origin, time, sampler)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+}
+
+pub fn frame_time(frame: i32, fps: f64) -> f64 {
+    frame as f64 / fps
+}
+
+// Replaces the last run of '#' in the pattern with the zero-padded frame
+// number, or appends the number before the extension if there is none.
+pub fn frame_path(pattern: &str, frame: i32) -> String {
+    if let Some(end) = pattern.rfind('#') {
+        let start = pattern[..end].trim_end_matches('#').len();
+        let width = end + 1 - start;
+        return format!(
+            "{}{:0width$}{}",
+            &pattern[..start],
+            frame,
+            &pattern[end + 1..],
+            width = width
+        );
+    }
+
+    let path = Path::new(pattern);
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => {
+            let stem = &pattern[..pattern.len() - extension.len() - 1];
+            format!("{}_{:04}.{}", stem, frame, extension)
+        }
+        None => format!("{}_{:04}", pattern, frame),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::color::Color;
+    use crate::material::DiffuseLight;
+    use crate::quad::Quad;
+    use crate::sampler::IndependentSampler;
+
+    fn light_quad(q: Point3) -> Quad {
+        Quad::new(
+            q,
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Some(Arc::new(DiffuseLight::new(Color::new(1.0, 1.0, 1.0)))),
+        )
+    }
+
+    #[test]
+    fn animated_light_samples_like_the_moved_object() {
+        let offset = Vec3::new(0.5, 2.0, -0.5);
+        let mut animated = Animated::new(Box::new(light_quad(Point3::default())));
+        animated.translation = Track::default().key(0.0, offset);
+        animated.rotation = Track::default().key(0.0, Vec3::new(0.0, 90.0, 0.0));
+        let moved = light_quad(offset + Vec3::new(0.0, 0.0, -1.0));
+        assert!(animated.is_emissive());
+
+        let origin = Point3::new(0.2, 0.0, 0.1);
+        let mut sampler = IndependentSampler::new(7);
+        for _ in 0..32 {
+            let direction = animated.random(origin, 0.0, &mut sampler);
+            let ray = Ray::new(origin, direction, 0.0);
+            let pdf = animated.pdf_value(&ray);
+            assert!(pdf > 0.0);
+            assert!((pdf - moved.pdf_value(&ray)).abs() < 1e-9 * pdf);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::distribution::Distribution2D;
+use crate::image::Image;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+#[derive(Clone, Debug, Default)]
+pub enum ApertureShape {
+    #[default]
+    Circle,
+    Polygon {
+        blades: u32,
+        rotation: f64,
+    },
+    Image(Arc<Distribution2D>),
+}
+
+impl ApertureShape {
+    // The image is stretched over the square enclosing the unit disk and
+    // sampled in proportion to its luminance.
+    pub fn from_image(image: &Image) -> Self {
+        let weights: Vec<f64> = image.pixels().iter().map(|p| p.luminance()).collect();
+        ApertureShape::Image(Arc::new(Distribution2D::new(
+            &weights,
+            image.width(),
+            image.height(),
+        )))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Aperture {
+    pub shape: ApertureShape,
+    pub cats_eye: f64,
+}
+
+impl Aperture {
+    // Returns a point on the aperture in lens coordinates, where the unit
+    // disk spans the full opening.
+    pub fn sample(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        match &self.shape {
+            ApertureShape::Circle => Vec3::random_in_unit_disk(sampler),
+            ApertureShape::Polygon { blades, rotation } => {
+                let blades = (*blades).max(3);
+                let (u, v) = sampler.get_2d();
+                let scaled = u * blades as f64;
+                let blade = (scaled as u32).min(blades - 1);
+                let s = (scaled - blade as f64).sqrt();
+
+                let angle0 = rotation.to_radians() + 2.0 * PI * blade as f64 / blades as f64;
+                let angle1 = angle0 + 2.0 * PI / blades as f64;
+                let p0 = Vec3::new(angle0.cos(), angle0.sin(), 0.0);
+                let p1 = Vec3::new(angle1.cos(), angle1.sin(), 0.0);
+                s * ((1.0 - v) * p0 + v * p1)
+            }
+            ApertureShape::Image(distribution) => {
+                let (u, v) = sampler.get_2d();
+                let ((x, y), _) = distribution.sample(u, v);
+                Vec3::new(2.0 * x - 1.0, 1.0 - 2.0 * y, 0.0)
+            }
+        }
+    }
+
+    // Off-axis points see the opening clipped by the lens barrel, modelled as
+    // a second unit disk shifted towards the film position (given relative to
+    // the image corner).
+    pub fn vignetted(&self, point: Vec3, film_x: f64, film_y: f64) -> bool {
+        if self.cats_eye <= 0.0 {
+            return false;
+        }
+        let offset = Vec3::new(self.cats_eye * film_x, self.cats_eye * film_y, 0.0);
+        (point - offset).length_squared() > 1.0
+    }
+}
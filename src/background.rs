@@ -0,0 +1,89 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::environment::EnvironmentMap;
+use crate::sampler::Sampler;
+use crate::sky::Sky;
+use crate::vec3::Vec3;
+
+#[derive(Clone, Debug)]
+pub enum Background {
+    Gradient { bottom: Color, top: Color },
+    Solid(Color),
+    Environment(Arc<EnvironmentMap>),
+    Sky(Sky),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+impl Background {
+    pub fn evaluate(&self, direction: Vec3) -> Color {
+        match self {
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.unit().y() + 1.0);
+                (1.0 - t) * *bottom + t * *top
+            }
+            Background::Solid(color) => *color,
+            Background::Environment(map) => map.evaluate(direction),
+            Background::Sky(sky) => sky.evaluate(direction),
+        }
+    }
+
+    // Only environment maps are sampled explicitly; the other backgrounds are
+    // smooth enough to be found by scattered rays alone.
+    pub fn sample(&self, sampler: &mut dyn Sampler) -> Option<(Vec3, Color, f64)> {
+        match self {
+            Background::Environment(map) => {
+                let (u, v) = sampler.get_2d();
+                map.sample(u, v)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn pdf(&self, direction: Vec3) -> f64 {
+        match self {
+            Background::Environment(map) => map.pdf(direction),
+            _ => 0.0,
+        }
+    }
+
+    // A directional light that scattered rays can never hit, so it is only
+    // ever sampled explicitly.
+    pub fn sun(&self) -> Option<(Vec3, Color)> {
+        match self {
+            Background::Sky(sky) => sky.sun(),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Background {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gradient" => Ok(Background::default()),
+            "black" => Ok(Background::Solid(Color::default())),
+            _ => {
+                let channels: Vec<f64> = s
+                    .split(',')
+                    .map(|value| value.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("unknown background: {}", s))?;
+                match channels[..] {
+                    [r, g, b] => Ok(Background::Solid(Color::new(r, g, b))),
+                    _ => Err(format!("unknown background: {}", s)),
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,145 @@
+use crate::aperture::Aperture;
+use crate::background::Background;
 use crate::color::Color;
+use crate::film::Film;
+use crate::filter::Filter;
 use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::image::Image;
+use crate::integrator::{Integrator, IntegratorKind, Scene};
 use crate::interval::Interval;
+use crate::lens::{LensSystem, Realistic};
+use crate::projection::{
+    Equirectangular, Fisheye, Frame, Orthographic, Perspective, Projection, ProjectionKind,
+};
 use crate::ray::Ray;
+use crate::sampler::SamplerKind;
 use crate::vec3::{Point3, Vec3};
 
-use rand::Rng;
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+const TILE_SIZE: i32 = 16;
+
+#[derive(Clone, Copy, Debug)]
+struct Tile {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveSampling {
+    pub min_samples: i32,
+    pub max_samples: i32,
+    pub threshold: f64,
+}
+
+impl AdaptiveSampling {
+    // Converged once the 95% confidence interval of the pixel luminance is
+    // within `threshold` of its mean.
+    fn converged(&self, stats: &RunningVariance) -> bool {
+        const MIN_LUMINANCE: f64 = 1e-2;
+        let error = 1.96 * (stats.variance() / stats.count as f64).sqrt();
+        // A single sample has no variance to judge by.
+        stats.count >= self.min_samples.max(2)
+            && error <= self.threshold * stats.mean.max(MIN_LUMINANCE)
+    }
+}
+
+// Pixel bounds with exclusive upper corner. The region is written either as a
+// smaller image or onto a transparent canvas the size of the full frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CropWindow {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+    pub full_canvas: bool,
+}
+
+impl FromStr for CropWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bounds: Vec<i32> = s
+            .split(',')
+            .map(|value| value.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid crop window: {}", s))?;
+        match bounds[..] {
+            [x0, y0, x1, y1] if x0 < x1 && y0 < y1 => Ok(CropWindow {
+                x0,
+                y0,
+                x1,
+                y1,
+                full_canvas: false,
+            }),
+            _ => Err(format!("invalid crop window: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FocusMode {
+    #[default]
+    Manual,
+    LookAt,
+    Center,
+    Pixel {
+        x: i32,
+        y: i32,
+    },
+}
+
+impl FromStr for FocusMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "manual" => Ok(FocusMode::Manual),
+            "lookat" => Ok(FocusMode::LookAt),
+            "center" => Ok(FocusMode::Center),
+            _ => s
+                .split_once(',')
+                .and_then(|(x, y)| {
+                    Some(FocusMode::Pixel {
+                        x: x.trim().parse().ok()?,
+                        y: y.trim().parse().ok()?,
+                    })
+                })
+                .ok_or_else(|| format!("unknown focus mode: {}", s)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct RunningVariance {
+    count: i32,
+    mean: f64,
+    m2: f64,
+}
+
+impl RunningVariance {
+    fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Camera {
@@ -12,22 +147,33 @@ pub struct Camera {
     pub image_width: i32,
     pub sample_per_pixel: i32,
     pub max_depth: i32,
+    pub roulette_depth: i32,
+    pub integrator: IntegratorKind,
     pub vfov: f64,
     pub lookfrom: Point3,
     pub lookat: Point3,
     pub vup: Vec3,
     pub defocus_angle: f64,
     pub focus_distance: f64,
+    pub focus: FocusMode,
+    pub aperture: Aperture,
+    pub alpha: bool,
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    pub adaptive: Option<AdaptiveSampling>,
+    pub filter: Filter,
+    pub background: Background,
+    pub crop: Option<CropWindow>,
+    pub projection: ProjectionKind,
+    pub lens: LensSystem,
+    pub shutter_open: f64,
+    pub shutter_close: f64,
     image_height: i32,
-    center: Point3,
-    pixel00_loc: Point3,
-    pixel_delta_u: Vec3,
-    pixel_delta_v: Vec3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
-    defocus_disk_u: Vec3,
-    defocus_disk_v: Vec3,
+    // The manual `focus_distance` or, for projections with a focus plane, the
+    // autofocus result of the current render.
+    focus_plane: f64,
+    projector: Box<dyn Projection>,
+    sample_counts: Vec<i32>,
 }
 
 impl Default for Camera {
@@ -37,131 +183,570 @@ impl Default for Camera {
             image_width: 100,
             sample_per_pixel: 10,
             max_depth: 10,
+            roulette_depth: 3,
+            integrator: IntegratorKind::default(),
             vfov: 90.0,
             lookfrom: Point3::new(0.0, 0.0, -1.0),
             lookat: Point3::default(),
             vup: Vec3::new(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_distance: 10.0,
+            focus: FocusMode::default(),
+            aperture: Aperture::default(),
+            alpha: false,
+            seed: 0,
+            sampler: SamplerKind::default(),
+            adaptive: None,
+            filter: Filter::default(),
+            background: Background::default(),
+            crop: None,
+            projection: ProjectionKind::default(),
+            lens: LensSystem::default(),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
             image_height: 0,
-            center: Point3::default(),
-            pixel00_loc: Point3::default(),
-            pixel_delta_u: Vec3::default(),
-            pixel_delta_v: Vec3::default(),
-            u: Vec3::default(),
-            v: Vec3::default(),
-            w: Vec3::default(),
-            defocus_disk_u: Vec3::default(),
-            defocus_disk_v: Vec3::default(),
+            focus_plane: 0.0,
+            projector: Box::new(Perspective::default()),
+            sample_counts: Vec::new(),
         }
     }
 }
 
 impl Camera {
-    pub fn render(&mut self, world: &impl Hittable) {
-        self.initialize();
-
-        println!("P3");
-        println!("{} {}", self.image_width, self.image_height);
-        println!("255");
-
-        for j in 0..self.image_height {
-            eprint!(
-                "\rScanlines remaining: {:1$}",
-                (self.image_height - j),
-                self.image_height.ilog10() as usize + 1
-            );
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::default();
-                for _ in 0..self.sample_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += self.ray_color(&ray, self.max_depth, world);
+    // `lights` holds the emitters that are sampled explicitly at each bounce;
+    // they must also be part of `world` to be seen.
+    pub fn render(&mut self, world: &impl Hittable, lights: &HittableList) -> io::Result<Image> {
+        self.initialize(world)?;
+        let integrator = self.integrator.create(self.max_depth, self.roulette_depth);
+        let scene = Scene {
+            world,
+            lights,
+            background: &self.background,
+        };
+
+        let region = self.region();
+        let tiles = self.tiles(&region);
+        let remaining = AtomicUsize::new(tiles.len());
+        let digits = tiles.len().max(1).ilog10() as usize + 1;
+        let rendered: Vec<(Film, Vec<i32>)> = tiles
+            .par_iter()
+            .map(|tile| {
+                let tile_film = self.render_tile(tile, integrator.as_ref(), &scene);
+                let left = remaining.fetch_sub(1, Ordering::Relaxed) - 1;
+                eprint!("\rTiles remaining: {:1$}", left, digits);
+                tile_film
+            })
+            .collect();
+
+        // Tiles are merged in a fixed order so that overlapping filter
+        // footprints sum identically regardless of scheduling.
+        let mut film = Film::new(
+            region.x0,
+            region.y0,
+            region.x1 - region.x0,
+            region.y1 - region.y0,
+            self.filter,
+        );
+        self.sample_counts = vec![0; (self.image_width * self.image_height) as usize];
+        for (tile, (tile_film, tile_counts)) in tiles.iter().zip(rendered) {
+            film.merge(&tile_film);
+            let mut tile_counts = tile_counts.into_iter();
+            for j in tile.y0..tile.y1 {
+                for i in tile.x0..tile.x1 {
+                    self.sample_counts[(j * self.image_width + i) as usize] =
+                        tile_counts.next().unwrap();
                 }
-                println!("{}", pixel_color.color_str(self.sample_per_pixel));
             }
         }
 
         eprint!("\rDone.                                \n");
+        Ok(self.place_on_canvas(film.to_image(), &region))
     }
 
-    fn initialize(&mut self) {
-        self.image_height = (self.image_width as f64 / self.aspect_ratio) as i32;
-        if self.image_height < 1 {
-            self.image_height = 1;
+    fn region(&self) -> Tile {
+        match self.crop {
+            Some(crop) => {
+                let x0 = crop.x0.clamp(0, self.image_width);
+                let y0 = crop.y0.clamp(0, self.image_height);
+                Tile {
+                    x0,
+                    y0,
+                    x1: crop.x1.clamp(x0, self.image_width),
+                    y1: crop.y1.clamp(y0, self.image_height),
+                }
+            }
+            None => Tile {
+                x0: 0,
+                y0: 0,
+                x1: self.image_width,
+                y1: self.image_height,
+            },
         }
+    }
 
-        self.center = self.lookfrom;
+    // Tiles stay on the full-frame grid and reach past the region by the
+    // filter margin, so cropped pixels match a full render exactly.
+    fn tiles(&self, region: &Tile) -> Vec<Tile> {
+        if region.x0 >= region.x1 || region.y0 >= region.y1 {
+            return Vec::new();
+        }
 
-        let theta = self.vfov.to_radians();
-        let half_height = (theta / 2.0).tan();
-        let viewport_height = 2.0 * half_height * self.focus_distance;
-        let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
+        let margin = self.filter_margin();
+        let x_begin = (region.x0 - margin).max(0);
+        let y_begin = (region.y0 - margin).max(0);
+        let x_end = (region.x1 + margin).min(self.image_width);
+        let y_end = (region.y1 + margin).min(self.image_height);
 
-        self.w = (self.lookfrom - self.lookat).unit();
-        self.u = self.vup.cross(self.w).unit();
-        self.v = self.w.cross(self.u);
+        let mut tiles = Vec::new();
+        let first_row = y_begin / TILE_SIZE * TILE_SIZE;
+        let first_column = x_begin / TILE_SIZE * TILE_SIZE;
+        for y0 in (first_row..y_end).step_by(TILE_SIZE as usize) {
+            for x0 in (first_column..x_end).step_by(TILE_SIZE as usize) {
+                tiles.push(Tile {
+                    x0: x0.max(x_begin),
+                    y0: y0.max(y_begin),
+                    x1: (x0 + TILE_SIZE).min(x_end),
+                    y1: (y0 + TILE_SIZE).min(y_end),
+                });
+            }
+        }
+        tiles
+    }
 
-        let viewport_u = viewport_width * self.u;
-        let viewport_v = viewport_height * -self.v;
+    fn place_on_canvas(&self, image: Image, region: &Tile) -> Image {
+        if !self.crop.is_some_and(|crop| crop.full_canvas) {
+            return image;
+        }
 
-        self.pixel_delta_u = viewport_u / self.image_width as f64;
-        self.pixel_delta_v = viewport_v / self.image_height as f64;
+        let mut canvas = Image::new(self.image_width as usize, self.image_height as usize);
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                canvas.set_alpha(x, y, 0.0);
+            }
+        }
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let canvas_x = x + region.x0 as usize;
+                let canvas_y = y + region.y0 as usize;
+                canvas[(canvas_x, canvas_y)] = image[(x, y)];
+                canvas.set_alpha(canvas_x, canvas_y, image.alpha(x, y));
+            }
+        }
+        canvas
+    }
 
-        let viewport_upper_left =
-            self.center - self.focus_distance * self.w - viewport_u / 2.0 - viewport_v / 2.0;
-        self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+    pub fn sample_count_heatmap(&self) -> Image {
+        let region = self.region();
+        let count = |x: i32, y: i32| self.sample_counts[(y * self.image_width + x) as usize];
+        // Tiles also count the filter margin around the region, which isn't
+        // part of the heatmap.
+        let max_count = (region.y0..region.y1)
+            .flat_map(|y| (region.x0..region.x1).map(move |x| count(x, y)))
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let mut heatmap = Image::new(
+            (region.x1 - region.x0) as usize,
+            (region.y1 - region.y0) as usize,
+        );
+        for y in region.y0..region.y1 {
+            for x in region.x0..region.x1 {
+                heatmap[((x - region.x0) as usize, (y - region.y0) as usize)] =
+                    Color::heatmap(count(x, y) as f64 / max_count as f64);
+            }
+        }
+        self.place_on_canvas(heatmap, &region)
+    }
 
-        let defocus_radius = self.focus_distance * (self.defocus_angle / 2.0).to_radians().tan();
-        self.defocus_disk_u = defocus_radius * self.u;
-        self.defocus_disk_v = defocus_radius * self.v;
+    fn filter_margin(&self) -> i32 {
+        (self.filter.radius() - 0.5).ceil().max(0.0) as i32
     }
 
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
-        let pixel_center =
-            self.pixel00_loc + self.pixel_delta_u * i as f64 + self.pixel_delta_v * j as f64;
-        let pixel_sample = pixel_center + self.pixel_sample_square();
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        integrator: &dyn Integrator,
+        scene: &Scene,
+    ) -> (Film, Vec<i32>) {
+        let margin = self.filter_margin();
+        let x0 = (tile.x0 - margin).max(0);
+        let y0 = (tile.y0 - margin).max(0);
+        let x1 = (tile.x1 + margin).min(self.image_width);
+        let y1 = (tile.y1 + margin).min(self.image_height);
+        let mut film = Film::new(x0, y0, x1 - x0, y1 - y0, self.filter);
+        let mut counts = Vec::with_capacity(((tile.x1 - tile.x0) * (tile.y1 - tile.y0)) as usize);
+        let max_samples = match self.adaptive {
+            Some(adaptive) => adaptive.max_samples,
+            None => self.sample_per_pixel,
+        };
+        let mut sampler = self.sampler.create(self.seed, max_samples as u32);
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                let mut stats = RunningVariance::default();
+                while stats.count < max_samples {
+                    sampler.start_pixel_sample(i, j, stats.count as u32);
+                    let (px, py) = sampler.get_2d();
+                    let film_x = i as f64 + px;
+                    let film_y = j as f64 + py;
+                    let time = self.shutter_open
+                        + sampler.get_1d() * (self.shutter_close - self.shutter_open);
+
+                    let ray = self
+                        .projector
+                        .generate_ray(film_x, film_y, time, sampler.as_mut());
+                    let (sample_color, coverage) = match ray {
+                        Some(ray) => {
+                            let coverage = self.coverage(&ray, scene.world);
+                            (integrator.radiance(ray, scene, sampler.as_mut()), coverage)
+                        }
+                        None => (Color::default(), 0.0),
+                    };
+                    // Weighting by coverage keeps the background out of
+                    // partly covered pixels when rendering with alpha.
+                    film.add_sample(film_x, film_y, coverage * sample_color, coverage);
+                    stats.add(sample_color.luminance());
+
+                    if self
+                        .adaptive
+                        .is_some_and(|adaptive| adaptive.converged(&stats))
+                    {
+                        break;
+                    }
+                }
+
+                counts.push(stats.count);
+            }
+        }
+        (film, counts)
+    }
 
-        let ray_origin = if self.defocus_angle <= 0.0 {
-            self.center
+    fn coverage(&self, ray: &Ray, world: &dyn Hittable) -> f64 {
+        if !self.alpha
+            || world
+                .hit(ray, Interval::new(0.001, f64::INFINITY))
+                .is_some()
+        {
+            1.0
         } else {
-            self.defocus_disk_sample()
+            0.0
+        }
+    }
+
+    fn initialize(&mut self, world: &impl Hittable) -> io::Result<()> {
+        self.image_height = (self.image_width as f64 / self.aspect_ratio) as i32;
+        if self.image_height < 1 {
+            self.image_height = 1;
+        }
+
+        if let Some(crop) = self.crop {
+            if crop.x0 >= crop.x1 || crop.y0 >= crop.y1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "crop window {},{},{},{} is empty",
+                        crop.x0, crop.y0, crop.x1, crop.y1
+                    ),
+                ));
+            }
+            if crop.x0 < 0
+                || crop.y0 < 0
+                || crop.x1 > self.image_width
+                || crop.y1 > self.image_height
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "crop window {},{},{},{} lies outside the {}x{} frame",
+                        crop.x0, crop.y0, crop.x1, crop.y1, self.image_width, self.image_height
+                    ),
+                ));
+            }
+        }
+
+        if let FocusMode::Pixel { x, y } = self.focus {
+            if x < 0 || y < 0 || x >= self.image_width || y >= self.image_height {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "focus pixel {},{} lies outside the {}x{} frame",
+                        x, y, self.image_width, self.image_height
+                    ),
+                ));
+            }
+        }
+
+        let frame = Frame::look_at(self.lookfrom, self.lookat, self.vup);
+        self.focus_plane = match self.projection {
+            ProjectionKind::Perspective | ProjectionKind::Realistic => {
+                self.autofocus(frame, world).unwrap_or(self.focus_distance)
+            }
+            _ => self.focus_distance,
+        };
+        self.projector = match self.projection {
+            ProjectionKind::Perspective => Box::new(Perspective::new(
+                frame,
+                self.image_width,
+                self.image_height,
+                self.vfov,
+                self.focus_plane,
+                self.defocus_angle,
+                self.aperture.clone(),
+            )),
+            ProjectionKind::Orthographic => Box::new(Orthographic::new(
+                frame,
+                self.image_width,
+                self.image_height,
+                self.vfov,
+                self.focus_distance,
+            )),
+            ProjectionKind::Fisheye { fov } => Box::new(Fisheye::new(
+                frame,
+                self.image_width,
+                self.image_height,
+                fov,
+            )),
+            ProjectionKind::Equirectangular => Box::new(Equirectangular::new(
+                frame,
+                self.image_width,
+                self.image_height,
+            )),
+            ProjectionKind::Realistic => Box::new(Realistic::new(
+                frame,
+                self.image_width,
+                self.image_height,
+                self.vfov,
+                self.focus_plane,
+                &self.lens,
+            )?),
         };
-        let ray_direction = pixel_sample - ray_origin;
+        Ok(())
+    }
 
-        Ray::new(ray_origin, ray_direction)
+    // Distance along the view axis to the focus target. Probe pixels are traced
+    // through a pinhole with the same field of view, which is exact for the
+    // perspective camera and the thin lens limit of a realistic one. A probe
+    // that hits nothing leaves the manual distance in place.
+    fn autofocus(&self, frame: Frame, world: &impl Hittable) -> Option<f64> {
+        let (x, y) = match self.focus {
+            FocusMode::Manual => return None,
+            FocusMode::LookAt => return Some((self.lookat - self.lookfrom).length()),
+            FocusMode::Center => (self.image_width / 2, self.image_height / 2),
+            FocusMode::Pixel { x, y } => (x, y),
+        };
+
+        let pinhole = Perspective::new(
+            frame,
+            self.image_width,
+            self.image_height,
+            self.vfov,
+            1.0,
+            0.0,
+            Aperture::default(),
+        );
+        let mut sampler = self.sampler.create(self.seed, 1);
+        let ray = pinhole.generate_ray(
+            x as f64 + 0.5,
+            y as f64 + 0.5,
+            self.shutter_open,
+            sampler.as_mut(),
+        )?;
+        let record = world.hit(&ray, Interval::new(0.001, f64::INFINITY))?;
+        Some((record.p - frame.origin).dot(-frame.w))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
 
-    fn defocus_disk_sample(&self) -> Point3 {
-        let p = Vec3::random_in_unit_disk();
-        self.center + self.defocus_disk_u * p.x() + self.defocus_disk_v * p.y()
+    fn camera() -> Camera {
+        Camera {
+            image_width: 9,
+            sample_per_pixel: 4,
+            lookfrom: Point3::default(),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            ..Camera::default()
+        }
     }
 
-    fn pixel_sample_square(&self) -> Vec3 {
-        let mut rng = rand::thread_rng();
-        let px = rng.gen_range(-0.5..=0.5);
-        let py = rng.gen_range(-0.5..=0.5);
-        px * self.pixel_delta_u + py * self.pixel_delta_v
+    fn sphere_at(z: f64) -> HittableList {
+        let mut world = HittableList::default();
+        world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, z), 0.5, None)));
+        world
     }
 
-    fn ray_color(&self, r: &Ray, depth: i32, world: &impl Hittable) -> Color {
-        if depth <= 0 {
-            return Color::default();
+    fn assert_same_pixels(a: &Image, b: &Image, (x0, y0): (usize, usize)) {
+        for y in 0..b.height() {
+            for x in 0..b.width() {
+                let (p, q) = (a[(x + x0, y + y0)], b[(x, y)]);
+                assert_eq!((p.x(), p.y(), p.z()), (q.x(), q.y(), q.z()));
+            }
         }
+    }
 
-        match world.hit(r, Interval::new(0.001, f64::INFINITY)) {
-            Some(record) => match record.mat.as_ref().and_then(|mat| mat.scatter(r, &record)) {
-                Some((attenuation, scattered)) => {
-                    attenuation * self.ray_color(&scattered, depth - 1, world)
+    #[test]
+    fn tiles_cover_every_pixel_once() {
+        let mut camera = Camera {
+            aspect_ratio: 37.0 / 21.0,
+            image_width: 37,
+            ..Camera::default()
+        };
+        camera.initialize(&HittableList::default()).unwrap();
+        assert_eq!(camera.image_height, 21);
+
+        let mut covered = vec![0; 37 * 21];
+        for tile in camera.tiles(&camera.region()) {
+            for j in tile.y0..tile.y1 {
+                for i in tile.x0..tile.x1 {
+                    covered[(j * 37 + i) as usize] += 1;
                 }
-                None => Color::default(),
-            },
-            None => {
-                let unit_direction = r.direction().unit();
-                let t = 0.5 * (unit_direction.y() + 1.0);
-                let white = Color::new(1.0, 1.0, 1.0);
-                let blue = Color::new(0.5, 0.7, 1.0);
-                (1.0 - t) * white + t * blue
             }
         }
+        assert!(covered.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn render_is_independent_of_thread_count() {
+        let mut world = sphere_at(-1.5);
+        let ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.5),
+            100.0,
+            Some(ground),
+        )));
+        let render = |threads| {
+            let mut camera = Camera {
+                image_width: 40,
+                ..camera()
+            };
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| camera.render(&world, &HittableList::default()))
+                .unwrap()
+        };
+        let single = render(1);
+        assert_same_pixels(&single, &render(4), (0, 0));
+    }
+
+    #[test]
+    fn adaptive_sampling_stops_early_only_on_converged_pixels() {
+        let adaptive = |min_samples| AdaptiveSampling {
+            min_samples,
+            max_samples: 32,
+            threshold: 1e-6,
+        };
+        // A sphere without a material around the camera makes every pixel
+        // black.
+        let mut enclosed = HittableList::default();
+        enclosed.add(Box::new(Sphere::new(Point3::default(), 10.0, None)));
+        let mut camera = Camera {
+            adaptive: Some(adaptive(4)),
+            ..camera()
+        };
+        camera.render(&enclosed, &HittableList::default()).unwrap();
+        assert!(camera.sample_counts.iter().all(|&count| count == 4));
+
+        camera.adaptive = Some(adaptive(1));
+        camera.render(&enclosed, &HittableList::default()).unwrap();
+        assert!(camera.sample_counts.iter().all(|&count| count == 2));
+
+        let mut world = HittableList::default();
+        let diffuse = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -11.0),
+            10.0,
+            Some(diffuse),
+        )));
+        camera.render(&world, &HittableList::default()).unwrap();
+        assert!(camera.sample_counts.iter().all(|&count| count == 32));
+    }
+
+    #[test]
+    fn cropped_pixels_match_the_full_render() {
+        let world = sphere_at(-1.5);
+        let mut camera = Camera {
+            image_width: 40,
+            filter: "mitchell".parse().unwrap(),
+            ..camera()
+        };
+        let full = camera.render(&world, &HittableList::default()).unwrap();
+
+        camera.crop = Some(CropWindow {
+            x0: 13,
+            y0: 7,
+            x1: 29,
+            y1: 26,
+            full_canvas: false,
+        });
+        let cropped = camera.render(&world, &HittableList::default()).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (16, 19));
+        assert_same_pixels(&full, &cropped, (13, 7));
+    }
+
+    #[test]
+    fn rejects_empty_crop_windows() {
+        let mut camera = Camera {
+            crop: Some(CropWindow {
+                x0: 6,
+                y0: 2,
+                x1: 4,
+                y1: 8,
+                full_canvas: false,
+            }),
+            ..camera()
+        };
+        let error = camera.initialize(&sphere_at(-3.0)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn autofocus_leaves_the_manual_distance_alone() {
+        let mut camera = Camera {
+            focus: FocusMode::Center,
+            ..camera()
+        };
+        camera.initialize(&sphere_at(-3.0)).unwrap();
+        assert!((camera.focus_plane - 2.5).abs() < 1e-9);
+        assert_eq!(camera.focus_distance, 10.0);
+
+        camera.initialize(&HittableList::default()).unwrap();
+        assert_eq!(camera.focus_plane, 10.0);
+    }
+
+    #[test]
+    fn autofocus_keeps_orthographic_framing() {
+        let world = sphere_at(-3.0);
+        let corner_ray = |focus| {
+            let mut camera = Camera {
+                projection: ProjectionKind::Orthographic,
+                focus,
+                ..camera()
+            };
+            camera.initialize(&world).unwrap();
+            let mut sampler = camera.sampler.create(0, 1);
+            camera
+                .projector
+                .generate_ray(0.0, 0.0, 0.0, sampler.as_mut())
+                .unwrap()
+                .origin()
+        };
+        let (manual, focused) = (corner_ray(FocusMode::Manual), corner_ray(FocusMode::Center));
+        assert_eq!((manual.x(), manual.y()), (focused.x(), focused.y()));
+    }
+
+    #[test]
+    fn rejects_focus_pixel_outside_the_frame() {
+        let mut camera = Camera {
+            focus: FocusMode::Pixel { x: 9, y: 0 },
+            ..camera()
+        };
+        let error = camera.initialize(&sphere_at(-3.0)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
     }
 }
@@ -4,31 +4,41 @@ use crate::vec3::Vec3;
 pub type Color = Vec3;
 
 impl Color {
-    fn linear_to_gamma(x: f64) -> f64 {
-        x.sqrt()
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x() + 0.7152 * self.y() + 0.0722 * self.z()
     }
 
-    pub fn color_str(&self, samples_per_pixel: i32) -> String {
-        let mut r = self.x();
-        let mut g = self.y();
-        let mut b = self.z();
+    pub fn max_component(&self) -> f64 {
+        self.x().max(self.y()).max(self.z())
+    }
 
-        let scale = 1.0 / samples_per_pixel as f64;
-        r *= scale;
-        g *= scale;
-        b *= scale;
+    pub fn heatmap(t: f64) -> Color {
+        const STOPS: [(f64, f64, f64); 5] = [
+            (0.0, 0.0, 0.0),
+            (0.0, 0.0, 1.0),
+            (0.0, 1.0, 0.0),
+            (1.0, 1.0, 0.0),
+            (1.0, 0.0, 0.0),
+        ];
 
-        r = Self::linear_to_gamma(r);
-        g = Self::linear_to_gamma(g);
-        b = Self::linear_to_gamma(b);
+        let t = Interval::new(0.0, 1.0).clamp(t) * (STOPS.len() - 1) as f64;
+        let i = (t as usize).min(STOPS.len() - 2);
+        let f = t - i as f64;
+        let (r0, g0, b0) = STOPS[i];
+        let (r1, g1, b1) = STOPS[i + 1];
+        (1.0 - f) * Color::new(r0, g0, b0) + f * Color::new(r1, g1, b1)
+    }
 
-        const INTENSITY: Interval = Interval::new(0.0, 0.999);
+    pub fn quantize(x: f64, levels: f64) -> f64 {
+        const INTENSITY: Interval = Interval::new(0.0, 1.0 - f64::EPSILON);
+        (INTENSITY.clamp(x) * levels).floor()
+    }
+
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        [self.x(), self.y(), self.z()].map(|c| Self::quantize(c, 256.0) as u8)
+    }
 
-        format!(
-            "{} {} {}",
-            (INTENSITY.clamp(r) * 256.0) as i32,
-            (INTENSITY.clamp(g) * 256.0) as i32,
-            (INTENSITY.clamp(b) * 256.0) as i32,
-        )
+    pub fn to_rgb16(&self) -> [u16; 3] {
+        [self.x(), self.y(), self.z()].map(|c| Self::quantize(c, 65536.0) as u16)
     }
 }
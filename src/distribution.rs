@@ -0,0 +1,161 @@
+#[derive(Clone, Debug)]
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f64>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i].abs() / n as f64;
+        }
+
+        // A zero function falls back to a uniform distribution.
+        let integral = cdf[n];
+        if integral == 0.0 {
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as f64 / n as f64;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= integral;
+            }
+        }
+
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.func.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.func.is_empty()
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    // Returns a position in [0, 1), its density and the bucket it fell in.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let index = (self.cdf.partition_point(|&c| c <= u) - 1).min(self.len() - 1);
+        let mut du = u - self.cdf[index];
+        let width = self.cdf[index + 1] - self.cdf[index];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        let pdf = if self.integral > 0.0 {
+            self.func[index].abs() / self.integral
+        } else {
+            1.0
+        };
+        ((index as f64 + du) / self.len() as f64, pdf, index)
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        if self.integral == 0.0 {
+            return 1.0;
+        }
+        let index = ((x * self.len() as f64) as usize).min(self.len() - 1);
+        self.func[index].abs() / self.integral
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    // `func` is laid out row by row, with `width` entries per row.
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        let conditional: Vec<Distribution1D> = func
+            .chunks(width)
+            .take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|row| row.integral()).collect());
+        Self {
+            conditional,
+            marginal,
+        }
+    }
+
+    // Returns a point in [0, 1)^2 and its density with respect to area.
+    pub fn sample(&self, u: f64, v: f64) -> ((f64, f64), f64) {
+        let (y, pdf_y, row) = self.marginal.sample(v);
+        let (x, pdf_x, _) = self.conditional[row].sample(u);
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, x: f64, y: f64) -> f64 {
+        let row = ((y * self.marginal.len() as f64) as usize).min(self.marginal.len() - 1);
+        self.marginal.pdf(y) * self.conditional[row].pdf(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_follow_the_function() {
+        let distribution = Distribution1D::new(vec![1.0, 3.0, 0.0, 4.0]);
+        assert_eq!(distribution.integral(), 2.0);
+
+        let (x, pdf, index) = distribution.sample(0.0);
+        assert_eq!((x, pdf, index), (0.0, 0.5, 0));
+        let (x, pdf, index) = distribution.sample(0.3);
+        assert_eq!((pdf, index), (1.5, 1));
+        assert!((x - 0.25 * (1.0 + 0.175 / 0.375)).abs() < 1e-12);
+        let (_, _, index) = distribution.sample(0.99);
+        assert_eq!(index, 3);
+    }
+
+    #[test]
+    fn pdf_matches_sample() {
+        let distribution = Distribution1D::new(vec![0.2, 0.0, 5.0, 1.0, 2.5]);
+        for i in 0..100 {
+            let (x, pdf, _) = distribution.sample((i as f64 + 0.5) / 100.0);
+            assert!(pdf > 0.0);
+            assert!((distribution.pdf(x) - pdf).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn zero_function_is_uniform() {
+        let distribution = Distribution1D::new(vec![0.0; 4]);
+        let (x, pdf, index) = distribution.sample(0.6);
+        assert_eq!((pdf, index), (1.0, 2));
+        assert!((x - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pdf_2d_matches_sample_and_integrates_to_one() {
+        let func = [1.0, 2.0, 0.0, 4.0, 0.5, 0.5];
+        let distribution = Distribution2D::new(&func, 3, 2);
+        for i in 0..10 {
+            for j in 0..10 {
+                let (u, v) = ((i as f64 + 0.5) / 10.0, (j as f64 + 0.5) / 10.0);
+                let ((x, y), pdf) = distribution.sample(u, v);
+                assert!((distribution.pdf(x, y) - pdf).abs() < 1e-12);
+            }
+        }
+
+        let cells = func.len() as f64;
+        let total: f64 = (0..6)
+            .map(|i| distribution.pdf((i % 3) as f64 / 3.0 + 0.1, (i / 3) as f64 / 2.0 + 0.1))
+            .sum();
+        assert!((total / cells - 1.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,138 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::distribution::Distribution2D;
+use crate::image::Image;
+use crate::vec3::Vec3;
+
+// Equirectangular map with +y at the top row and -z at the horizontal centre,
+// matching the equirectangular camera looking down -z.
+#[derive(Debug)]
+pub struct EnvironmentMap {
+    image: Image,
+    distribution: Distribution2D,
+    rotation: f64,
+    intensity: f64,
+}
+
+impl EnvironmentMap {
+    // `rotation` turns the map about the y axis, in degrees.
+    pub fn new(image: Image, rotation: f64, intensity: f64) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let mut weights = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            for x in 0..width {
+                weights.push(image[(x, y)].luminance().max(0.0) * sin_theta);
+            }
+        }
+
+        Self {
+            distribution: Distribution2D::new(&weights, width, height),
+            image,
+            rotation: rotation.to_radians(),
+            intensity,
+        }
+    }
+
+    pub fn evaluate(&self, direction: Vec3) -> Color {
+        let (u, v) = self.to_uv(direction);
+        let x = ((u * self.image.width() as f64) as usize).min(self.image.width() - 1);
+        let y = ((v * self.image.height() as f64) as usize).min(self.image.height() - 1);
+        self.intensity * self.image[(x, y)]
+    }
+
+    // Picks a direction in proportion to the map's luminance. Returns the
+    // direction, its radiance and its solid angle density.
+    pub fn sample(&self, u: f64, v: f64) -> Option<(Vec3, Color, f64)> {
+        let ((x, y), map_pdf) = self.distribution.sample(u, v);
+        let theta = PI * y;
+        let sin_theta = theta.sin();
+        if map_pdf == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+
+        let direction = self.direction(x, y);
+        let pdf = map_pdf / (2.0 * PI * PI * sin_theta);
+        Some((direction, self.evaluate(direction), pdf))
+    }
+
+    pub fn pdf(&self, direction: Vec3) -> f64 {
+        let (u, v) = self.to_uv(direction);
+        let sin_theta = (PI * v).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn to_uv(&self, direction: Vec3) -> (f64, f64) {
+        let direction = direction.unit();
+        let theta = direction.y().clamp(-1.0, 1.0).acos();
+        let phi = direction.x().atan2(-direction.z()) - self.rotation;
+        let u = (phi / (2.0 * PI) + 0.5).rem_euclid(1.0);
+        (u, theta / PI)
+    }
+
+    fn direction(&self, u: f64, v: f64) -> Vec3 {
+        let theta = PI * v;
+        let phi = 2.0 * PI * (u - 0.5) + self.rotation;
+        Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> EnvironmentMap {
+        let mut image = Image::new(8, 4);
+        for y in 0..4 {
+            for x in 0..8 {
+                let value = ((x * 3 + y * 5) % 7) as f64;
+                image[(x, y)] = Color::new(value, 0.5 * value, 1.0);
+            }
+        }
+        EnvironmentMap::new(image, 30.0, 2.0)
+    }
+
+    #[test]
+    fn sample_pdf_matches_pdf_of_direction() {
+        let map = map();
+        for i in 0..20 {
+            for j in 0..20 {
+                let (u, v) = ((i as f64 + 0.5) / 20.0, (j as f64 + 0.5) / 20.0);
+                let Some((direction, radiance, pdf)) = map.sample(u, v) else {
+                    continue;
+                };
+                assert!((map.pdf(direction) / pdf - 1.0).abs() < 1e-9);
+                assert!((map.evaluate(direction).x() - radiance.x()).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_one_over_the_sphere() {
+        let map = map();
+        let n = 240;
+        let (d_theta, d_phi) = (PI / n as f64, 2.0 * PI / n as f64);
+        let mut integral = 0.0;
+        for i in 0..n {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..n {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                integral += map.pdf(direction) * theta.sin() * d_theta * d_phi;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-3, "{}", integral);
+    }
+}
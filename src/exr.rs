@@ -0,0 +1,193 @@
+use std::io::{self, Write};
+
+use half::f16;
+
+use crate::image::Image;
+
+const MAGIC: u32 = 20000630;
+const VERSION: u32 = 2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PixelType {
+    #[default]
+    Half,
+    Float,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExrOptions {
+    pub pixel_type: PixelType,
+    pub alpha: bool,
+}
+
+pub fn write_exr(image: &Image, out: &mut impl Write, options: ExrOptions) -> io::Result<()> {
+    // Channels must be stored in alphabetical order.
+    let channels: &[&str] = if options.alpha {
+        &["A", "B", "G", "R"]
+    } else {
+        &["B", "G", "R"]
+    };
+
+    let width = image.width() as i32;
+    let height = image.height() as i32;
+
+    let mut header = Vec::new();
+    header.extend(MAGIC.to_le_bytes());
+    header.extend(VERSION.to_le_bytes());
+
+    let mut chlist = Vec::new();
+    for name in channels {
+        chlist.extend(name.as_bytes());
+        chlist.push(0);
+        chlist.extend(options.pixel_type.id().to_le_bytes());
+        chlist.extend([0, 0, 0, 0]);
+        chlist.extend(1i32.to_le_bytes());
+        chlist.extend(1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    write_attribute(&mut header, "channels", "chlist", &chlist);
+    write_attribute(&mut header, "compression", "compression", &[0]);
+
+    let window: Vec<u8> = [0, 0, width - 1, height - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    let line_size = image.width() * channels.len() * options.pixel_type.size();
+    let block_size = 8 + line_size;
+    let first_block = header.len() + 8 * image.height();
+    for y in 0..image.height() {
+        header.extend(((first_block + y * block_size) as u64).to_le_bytes());
+    }
+    out.write_all(&header)?;
+
+    let mut block = Vec::with_capacity(block_size);
+    for y in 0..image.height() {
+        block.clear();
+        block.extend((y as i32).to_le_bytes());
+        block.extend((line_size as i32).to_le_bytes());
+        for name in channels {
+            for x in 0..image.width() {
+                let pixel = image[(x, y)];
+                let value = match *name {
+                    "A" => image.alpha(x, y),
+                    "B" => pixel.z(),
+                    "G" => pixel.y(),
+                    _ => pixel.x(),
+                };
+                match options.pixel_type {
+                    PixelType::Half => block.extend(f16::from_f64(value).to_le_bytes()),
+                    PixelType::Float => block.extend((value as f32).to_le_bytes()),
+                }
+            }
+        }
+        out.write_all(&block)?;
+    }
+
+    Ok(())
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend(name.as_bytes());
+    header.push(0);
+    header.extend(kind.as_bytes());
+    header.push(0);
+    header.extend((value.len() as i32).to_le_bytes());
+    header.extend(value);
+}
+
+impl PixelType {
+    fn id(self) -> i32 {
+        match self {
+            PixelType::Half => 1,
+            PixelType::Float => 2,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            PixelType::Half => 2,
+            PixelType::Float => 4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    fn encode(image: &Image, options: ExrOptions) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_exr(image, &mut data, options).unwrap();
+        data
+    }
+
+    #[test]
+    fn float_scanlines_hold_channels_in_order() {
+        let mut image = Image::new(2, 3);
+        image[(0, 1)] = Color::new(1.5, -2.0, 1e5);
+        image.set_alpha(1, 1, 0.25);
+        let options = ExrOptions {
+            pixel_type: PixelType::Float,
+            alpha: true,
+        };
+        let data = encode(&image, options);
+        assert_eq!(&data[..4], &MAGIC.to_le_bytes());
+
+        // The offset table sits right before the first of the equally sized
+        // scanline blocks.
+        let line_size = 2 * 4 * 4;
+        let first_block = data.len() - 3 * (8 + line_size);
+        let table = first_block - 3 * 8;
+        let offset = |y: usize| {
+            let bytes = data[table + 8 * y..table + 8 * y + 8].try_into().unwrap();
+            u64::from_le_bytes(bytes) as usize
+        };
+        assert_eq!(offset(0), first_block);
+
+        let block = offset(1);
+        let read = |i: usize| {
+            let start = block + 8 + 4 * i;
+            f32::from_le_bytes(data[start..start + 4].try_into().unwrap())
+        };
+        assert_eq!(
+            i32::from_le_bytes(data[block..block + 4].try_into().unwrap()),
+            1
+        );
+        // Channels are A, B, G, R, each holding the whole scanline.
+        assert_eq!([read(0), read(1)], [1.0, 0.25]);
+        assert_eq!([read(2), read(4), read(6)], [1e5, -2.0, 1.5]);
+    }
+
+    #[test]
+    fn half_scanlines_use_two_bytes_per_sample() {
+        let image = Image::new(4, 2);
+        let half = encode(&image, ExrOptions::default());
+        let float = encode(
+            &image,
+            ExrOptions {
+                pixel_type: PixelType::Float,
+                alpha: false,
+            },
+        );
+        assert_eq!(float.len() - half.len(), 2 * 4 * 3 * 2);
+    }
+}
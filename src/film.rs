@@ -0,0 +1,157 @@
+use crate::color::Color;
+use crate::filter::Filter;
+use crate::image::Image;
+
+#[derive(Clone, Debug)]
+pub struct Film {
+    x0: i32,
+    y0: i32,
+    width: i32,
+    height: i32,
+    filter: Filter,
+    color_sums: Vec<Color>,
+    alpha_sums: Vec<f64>,
+    weight_sums: Vec<f64>,
+}
+
+impl Film {
+    pub fn new(x0: i32, y0: i32, width: i32, height: i32, filter: Filter) -> Self {
+        let size = (width * height) as usize;
+        Self {
+            x0,
+            y0,
+            width,
+            height,
+            filter,
+            color_sums: vec![Color::default(); size],
+            alpha_sums: vec![0.0; size],
+            weight_sums: vec![0.0; size],
+        }
+    }
+
+    pub fn add_sample(&mut self, film_x: f64, film_y: f64, color: Color, alpha: f64) {
+        let radius = self.filter.radius();
+        let x_begin = ((film_x - 0.5 - radius).ceil() as i32).max(self.x0);
+        let x_end = ((film_x - 0.5 + radius).floor() as i32).min(self.x0 + self.width - 1);
+        let y_begin = ((film_y - 0.5 - radius).ceil() as i32).max(self.y0);
+        let y_end = ((film_y - 0.5 + radius).floor() as i32).min(self.y0 + self.height - 1);
+
+        for y in y_begin..=y_end {
+            for x in x_begin..=x_end {
+                let weight = self
+                    .filter
+                    .evaluate(x as f64 + 0.5 - film_x, y as f64 + 0.5 - film_y);
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let index = self.index(x, y);
+                self.color_sums[index] += weight * color;
+                self.alpha_sums[index] += weight * alpha;
+                self.weight_sums[index] += weight;
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &Film) {
+        for y in other.y0..other.y0 + other.height {
+            for x in other.x0..other.x0 + other.width {
+                if x < self.x0
+                    || x >= self.x0 + self.width
+                    || y < self.y0
+                    || y >= self.y0 + self.height
+                {
+                    continue;
+                }
+
+                let src = other.index(x, y);
+                let dst = self.index(x, y);
+                self.color_sums[dst] += other.color_sums[src];
+                self.alpha_sums[dst] += other.alpha_sums[src];
+                self.weight_sums[dst] += other.weight_sums[src];
+            }
+        }
+    }
+
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::new(self.width as usize, self.height as usize);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = (y * self.width + x) as usize;
+                let weight = self.weight_sums[index];
+                if weight.abs() < 1e-12 {
+                    image.set_alpha(x as usize, y as usize, 0.0);
+                    continue;
+                }
+                image[(x as usize, y as usize)] = self.color_sums[index] / weight;
+                image.set_alpha(x as usize, y as usize, self.alpha_sums[index] / weight);
+            }
+        }
+        image
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        ((y - self.y0) * self.width + (x - self.x0)) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn splat(film: &mut Film, samples: usize, color: Color) {
+        for i in 0..samples {
+            for j in 0..samples {
+                let x = (i as f64 + 0.5) / samples as f64 * 6.0;
+                let y = (j as f64 + 0.5) / samples as f64 * 6.0;
+                film.add_sample(x, y, color, 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn constant_radiance_survives_every_filter() {
+        let color = Color::new(0.25, 0.5, 2.0);
+        for name in ["box", "tent", "gaussian", "mitchell", "lanczos"] {
+            let mut film = Film::new(0, 0, 6, 6, name.parse().unwrap());
+            splat(&mut film, 37, color);
+            let image = film.to_image();
+            for y in 0..6 {
+                for x in 0..6 {
+                    let pixel = image[(x, y)];
+                    assert!((pixel.x() - color.x()).abs() < 1e-9, "{}", name);
+                    assert!((pixel.z() - color.z()).abs() < 1e-9, "{}", name);
+                    assert!((image.alpha(x, y) - 1.0).abs() < 1e-9, "{}", name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn merged_tiles_match_a_single_film() {
+        let filter: Filter = "mitchell".parse().unwrap();
+        let mut whole = Film::new(0, 0, 6, 6, filter);
+        let mut merged = Film::new(0, 0, 6, 6, filter);
+        let mut left = Film::new(-2, -2, 7, 10, filter);
+        let mut right = Film::new(1, -2, 7, 10, filter);
+        for (i, &(x, y)) in [(1.3, 2.2), (2.9, 4.1), (4.4, 0.7), (5.6, 5.2)]
+            .iter()
+            .enumerate()
+        {
+            let color = Color::new(i as f64, 1.0, 0.5);
+            whole.add_sample(x, y, color, 1.0);
+            let tile = if x < 3.0 { &mut left } else { &mut right };
+            tile.add_sample(x, y, color, 1.0);
+        }
+        merged.merge(&left);
+        merged.merge(&right);
+
+        let (whole, merged) = (whole.to_image(), merged.to_image());
+        for y in 0..6 {
+            for x in 0..6 {
+                assert!((whole[(x, y)].x() - merged[(x, y)].x()).abs() < 1e-12);
+                assert_eq!(whole.alpha(x, y), merged.alpha(x, y));
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Box { radius: f64 },
+    Tent { radius: f64 },
+    Gaussian { radius: f64, sigma: f64 },
+    Mitchell { radius: f64, b: f64, c: f64 },
+    Lanczos { radius: f64, tau: f64 },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    pub fn with_radius(self, radius: f64) -> Self {
+        match self {
+            Filter::Box { .. } => Filter::Box { radius },
+            Filter::Tent { .. } => Filter::Tent { radius },
+            Filter::Gaussian { sigma, .. } => Filter::Gaussian { radius, sigma },
+            Filter::Mitchell { b, c, .. } => Filter::Mitchell { radius, b, c },
+            Filter::Lanczos { tau, .. } => Filter::Lanczos { radius, tau },
+        }
+    }
+
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        if x > self.radius() {
+            return 0.0;
+        }
+
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { radius } => radius - x,
+            Filter::Gaussian { radius, sigma } => {
+                (gaussian(x, sigma) - gaussian(radius, sigma)).max(0.0)
+            }
+            Filter::Mitchell { radius, b, c } => mitchell(2.0 * x / radius, b, c),
+            Filter::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "box" => Ok(Filter::Box { radius: 0.5 }),
+            "tent" => Ok(Filter::Tent { radius: 1.0 }),
+            "gaussian" => Ok(Filter::Gaussian {
+                radius: 1.5,
+                sigma: 0.5,
+            }),
+            "mitchell" => Ok(Filter::Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            }),
+            "lanczos" => Ok(Filter::Lanczos {
+                radius: 3.0,
+                tau: 3.0,
+            }),
+            _ => Err(format!("unknown filter: {}", s)),
+        }
+    }
+}
+
+fn gaussian(x: f64, sigma: f64) -> f64 {
+    (-x * x / (2.0 * sigma * sigma)).exp() / (2.0 * PI * sigma * sigma).sqrt()
+}
+
+fn mitchell(x: f64, b: f64, c: f64) -> f64 {
+    let x = x.abs();
+    if x <= 1.0 {
+        ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b))
+            / 6.0
+    } else if x <= 2.0 {
+        ((-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c))
+            / 6.0
+    } else {
+        0.0
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [&str; 5] = ["box", "tent", "gaussian", "mitchell", "lanczos"];
+
+    #[test]
+    fn filters_vanish_outside_their_radius() {
+        for name in FILTERS {
+            let filter: Filter = name.parse().unwrap();
+            let radius = filter.radius();
+            assert!(filter.evaluate(0.0, 0.0) > 0.0, "{}", name);
+            assert_eq!(filter.evaluate(radius + 1e-9, 0.0), 0.0, "{}", name);
+            assert_eq!(filter.evaluate(0.0, -radius - 1e-9), 0.0, "{}", name);
+        }
+    }
+
+    #[test]
+    fn with_radius_keeps_the_shape_parameters() {
+        let filter: Filter = "mitchell".parse().unwrap();
+        assert_eq!(
+            filter.with_radius(4.0),
+            Filter::Mitchell {
+                radius: 4.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0
+            }
+        );
+    }
+
+    #[test]
+    fn gaussian_reaches_zero_at_its_radius() {
+        let filter: Filter = "gaussian".parse().unwrap();
+        assert_eq!(filter.evaluate(filter.radius(), 0.0), 0.0);
+        assert!(filter.evaluate(filter.radius() - 0.1, 0.0) > 0.0);
+    }
+}
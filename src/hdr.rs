@@ -0,0 +1,209 @@
+use std::io::{self, BufRead, Write};
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::ppm::{invalid_data, pixel_count};
+
+pub fn write_hdr(image: &Image, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "#?RADIANCE")?;
+    writeln!(out, "FORMAT=32-bit_rle_rgbe")?;
+    writeln!(out)?;
+    writeln!(out, "-Y {} +X {}", image.height(), image.width())?;
+
+    let mut data = Vec::with_capacity(image.pixels().len() * 4);
+    for pixel in image.pixels() {
+        data.extend(to_rgbe(*pixel));
+    }
+    out.write_all(&data)
+}
+
+// Non-finite radiance has no RGBE encoding and is written as black, while
+// values beyond the largest exponent saturate.
+fn to_rgbe(color: Color) -> [u8; 4] {
+    if ![color.x(), color.y(), color.z()]
+        .iter()
+        .all(|c| c.is_finite())
+    {
+        return [0, 0, 0, 0];
+    }
+    let r = color.x().max(0.0);
+    let g = color.y().max(0.0);
+    let b = color.z().max(0.0);
+
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    let exponent = (v.log2().floor() as i32 + 1).min(127);
+    let scale = 256.0 / 2f64.powi(exponent);
+    [
+        (r * scale).min(255.0) as u8,
+        (g * scale).min(255.0) as u8,
+        (b * scale).min(255.0) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+pub fn read_hdr(input: &mut impl BufRead) -> io::Result<Image> {
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid_data("missing Radiance HDR signature".to_string()));
+    }
+
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Err(invalid_data("unexpected end of HDR header".to_string()));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported HDR format: {}", format)));
+            }
+        }
+    }
+
+    line.clear();
+    input.read_line(&mut line)?;
+    let (height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (
+            height.parse().map_err(|_| invalid_data(line.clone()))?,
+            width.parse().map_err(|_| invalid_data(line.clone()))?,
+        ),
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported HDR orientation: {}",
+                line.trim()
+            )))
+        }
+    };
+
+    pixel_count(width, height)?;
+    let mut image = Image::new(width, height);
+    let mut scanline = vec![0; width * 4];
+    for y in 0..height {
+        read_scanline(input, &mut scanline)?;
+        for (x, rgbe) in scanline.chunks(4).enumerate() {
+            image[(x, y)] = from_rgbe([rgbe[0], rgbe[1], rgbe[2], rgbe[3]]);
+        }
+    }
+    Ok(image)
+}
+
+// Reads either a flat scanline or one using the adaptive run-length encoding,
+// which stores each of the four components separately.
+fn read_scanline(input: &mut impl BufRead, scanline: &mut [u8]) -> io::Result<()> {
+    let width = scanline.len() / 4;
+    let mut header = [0; 4];
+    input.read_exact(&mut header)?;
+    let encoded_width = ((header[2] as usize) << 8) | header[3] as usize;
+    if !(8..32768).contains(&width) || header[0] != 2 || header[1] != 2 || header[2] & 0x80 != 0 {
+        scanline[..4].copy_from_slice(&header);
+        return input.read_exact(&mut scanline[4..]);
+    }
+    if encoded_width != width {
+        return Err(invalid_data("HDR scanline width mismatch".to_string()));
+    }
+
+    let mut components = vec![0; width * 4];
+    for component in components.chunks_mut(width) {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0];
+            input.read_exact(&mut count)?;
+            let count = count[0] as usize;
+            if count > 128 {
+                let run = count - 128;
+                if x + run > width {
+                    return Err(invalid_data("HDR run overflows scanline".to_string()));
+                }
+                let mut value = [0];
+                input.read_exact(&mut value)?;
+                component[x..x + run].fill(value[0]);
+                x += run;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(invalid_data("invalid HDR run length".to_string()));
+                }
+                input.read_exact(&mut component[x..x + count])?;
+                x += count;
+            }
+        }
+    }
+
+    for x in 0..width {
+        for c in 0..4 {
+            scanline[x * 4 + c] = components[c * width + x];
+        }
+    }
+    Ok(())
+}
+
+fn from_rgbe([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::default();
+    }
+    let scale = 2f64.powi(e as i32 - 136);
+    Color::new(
+        (r as f64 + 0.5) * scale,
+        (g as f64 + 0.5) * scale,
+        (b as f64 + 0.5) * scale,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_preserves_radiance() {
+        let mut image = Image::new(3, 2);
+        image[(0, 0)] = Color::new(0.25, 0.5, 1.0);
+        image[(1, 0)] = Color::new(12.0, 0.1, 3.5);
+        image[(2, 1)] = Color::new(1e-3, 2e-3, 0.0);
+
+        let mut data = Vec::new();
+        write_hdr(&image, &mut data).unwrap();
+        let decoded = read_hdr(&mut data.as_slice()).unwrap();
+
+        assert_eq!((decoded.width(), decoded.height()), (3, 2));
+        for (expected, actual) in image.pixels().iter().zip(decoded.pixels()) {
+            let tolerance = expected.max_component() / 128.0;
+            assert!((*expected - *actual).length() <= tolerance.max(1e-12));
+        }
+    }
+
+    #[test]
+    fn decodes_run_length_encoded_scanline() {
+        let mut data = vec![2, 2, 0, 8];
+        data.extend([128 + 8, 10]);
+        data.extend([8, 0, 1, 2, 3, 4, 5, 6, 7]);
+        data.extend([128 + 4, 1, 4, 9, 8, 7, 6]);
+        data.extend([128 + 8, 129]);
+
+        let mut scanline = vec![0; 8 * 4];
+        read_scanline(&mut data.as_slice(), &mut scanline).unwrap();
+        assert_eq!(&scanline[..8], &[10, 0, 1, 129, 10, 1, 1, 129]);
+        assert_eq!(&scanline[28..], &[10, 7, 6, 129]);
+    }
+
+    #[test]
+    fn rejects_run_past_end_of_scanline() {
+        let mut data = vec![2, 2, 0, 8];
+        data.extend([128 + 9, 10]);
+        let mut scanline = vec![0; 8 * 4];
+        assert!(read_scanline(&mut data.as_slice(), &mut scanline).is_err());
+    }
+
+    #[test]
+    fn non_finite_radiance_is_black() {
+        assert_eq!(to_rgbe(Color::new(f64::INFINITY, 1.0, 1.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(Color::new(f64::NAN, 1.0, 1.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(Color::new(1e300, 0.0, 0.0))[3], 255);
+    }
+}
@@ -1,20 +1,21 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{Point3, Vec3};
 
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
-    pub mat: Option<Rc<dyn Material>>,
+    pub mat: Option<Arc<dyn Material>>,
     pub t: f64,
     pub front_face: bool,
 }
 
 impl HitRecord {
-    pub fn new(p: Point3, normal: Vec3, t: f64, r: &Ray, mat: Option<Rc<dyn Material>>) -> Self {
+    pub fn new(p: Point3, normal: Vec3, t: f64, r: &Ray, mat: Option<Arc<dyn Material>>) -> Self {
         let front_face = r.direction().dot(normal) < 0.0;
         let normal = if front_face { normal } else { -normal };
         Self {
@@ -27,6 +28,45 @@ impl HitRecord {
     }
 }
 
-pub trait Hittable {
+pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, inteval: Interval) -> Option<HitRecord>;
+
+    // Solid angle density of `random` picking the direction of `r` from its
+    // origin. Objects that can't be sampled as lights report zero.
+    fn pdf_value(&self, _r: &Ray) -> f64 {
+        0.0
+    }
+
+    fn random(&self, _origin: Point3, _time: f64, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    // Whether the object gives off light and should be sampled as a light.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, r: &Ray, interval: Interval) -> Option<HitRecord> {
+        self.as_ref().hit(r, interval)
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        self.as_ref().pdf_value(r)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        self.as_ref().random(origin, time, sampler)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.as_ref().is_emissive()
+    }
+}
+
+pub(crate) fn is_emissive(material: &Option<Arc<dyn Material>>) -> bool {
+    material
+        .as_ref()
+        .is_some_and(|material| material.is_emissive())
 }
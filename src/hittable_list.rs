@@ -1,12 +1,16 @@
+use std::sync::Arc;
+
 use crate::{
     hittable::{HitRecord, Hittable},
     interval::Interval,
     ray::Ray,
+    sampler::Sampler,
+    vec3::{Point3, Vec3},
 };
 
 #[derive(Default)]
 pub struct HittableList {
-    objects: Vec<Box<dyn Hittable>>,
+    objects: Vec<Arc<dyn Hittable>>,
 }
 
 impl HittableList {
@@ -15,7 +19,27 @@ impl HittableList {
     }
 
     pub fn add(&mut self, object: Box<dyn Hittable>) {
-        self.objects.push(object);
+        self.objects.push(Arc::from(object));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    // The emissive objects, shared with this list, for sampling as lights.
+    pub fn lights(&self) -> HittableList {
+        HittableList {
+            objects: self
+                .objects
+                .iter()
+                .filter(|object| object.is_emissive())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn emitters(&self) -> impl Iterator<Item = &Arc<dyn Hittable>> {
+        self.objects.iter().filter(|object| object.is_emissive())
     }
 }
 
@@ -33,4 +57,31 @@ impl Hittable for HittableList {
 
         hit_record
     }
+
+    // Sampling picks one emissive object uniformly, so the density is the
+    // average over those.
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        let count = self.emitters().count();
+        if count == 0 {
+            return 0.0;
+        }
+        let sum: f64 = self.emitters().map(|object| object.pdf_value(r)).sum();
+        sum / count as f64
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let count = self.emitters().count();
+        if count == 0 {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = ((sampler.get_1d() * count as f64) as usize).min(count - 1);
+        self.emitters()
+            .nth(index)
+            .unwrap()
+            .random(origin, time, sampler)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.emitters().next().is_some()
+    }
 }
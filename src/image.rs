@@ -0,0 +1,156 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::ops::{Index, IndexMut};
+use std::path::Path;
+
+use crate::color::Color;
+use crate::exr::{self, ExrOptions, PixelType};
+use crate::hdr;
+use crate::pfm;
+use crate::png::{self, BitDepth, PngOptions};
+use crate::ppm;
+use crate::tonemap::DisplayTransform;
+
+// How an image is encoded on top of the display transform. Formats that
+// can't store a setting ignore it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SaveOptions {
+    pub bit_depth: BitDepth,
+    pub pixel_type: PixelType,
+    pub alpha: bool,
+}
+
+// Colours are premultiplied by alpha.
+#[derive(Clone, Debug)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    alpha: Vec<f64>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); width * height],
+            alpha: vec![1.0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn max_component(&self) -> f64 {
+        self.pixels
+            .iter()
+            .map(|pixel| pixel.max_component())
+            .fold(0.0, f64::max)
+    }
+
+    pub fn scale(&mut self, factor: f64) {
+        for pixel in &mut self.pixels {
+            *pixel *= factor;
+        }
+    }
+
+    pub fn alpha(&self, x: usize, y: usize) -> f64 {
+        self.alpha[y * self.width + x]
+    }
+
+    pub fn set_alpha(&mut self, x: usize, y: usize, alpha: f64) {
+        self.alpha[y * self.width + x] = alpha;
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Image> {
+        let path = path.as_ref();
+        let mut input = BufReader::new(File::open(path)?);
+        let image = match extension(path).as_deref() {
+            Some("ppm") => ppm::read_ppm(&mut input)?,
+            Some("hdr") => hdr::read_hdr(&mut input)?,
+            Some("pfm") => pfm::read_pfm(&mut input)?,
+            _ => return Err(unsupported_format(path)),
+        };
+        // Environment maps and apertures build sampling distributions over the
+        // pixels, which need at least one.
+        if image.width == 0 || image.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("image has no pixels: {}", path.display()),
+            ));
+        }
+        Ok(image)
+    }
+
+    pub fn save(
+        &self,
+        path: impl AsRef<Path>,
+        display: &DisplayTransform,
+        options: SaveOptions,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let png_options = PngOptions {
+            bit_depth: options.bit_depth,
+            alpha: options.alpha,
+        };
+        let exr_options = ExrOptions {
+            pixel_type: options.pixel_type,
+            alpha: options.alpha,
+        };
+        let mut out = BufWriter::new(File::create(path)?);
+        match extension(path).as_deref() {
+            Some("ppm") => ppm::write_p6(self, &mut out, display)?,
+            Some("png") => png::write_png(self, &mut out, display, png_options)?,
+            Some("hdr") => hdr::write_hdr(self, &mut out)?,
+            Some("pfm") => pfm::write_pfm(self, &mut out)?,
+            Some("exr") => exr::write_exr(self, &mut out, exr_options)?,
+            _ => return Err(unsupported_format(path)),
+        }
+        out.flush()
+    }
+}
+
+// Whether `save` writes the path in a format that keeps values above one.
+pub fn is_float_format(path: impl AsRef<Path>) -> bool {
+    matches!(
+        extension(path.as_ref()).as_deref(),
+        Some("hdr" | "pfm" | "exr")
+    )
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+}
+
+fn unsupported_format(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("unsupported image format: {}", path.display()),
+    )
+}
+
+impl Index<(usize, usize)> for Image {
+    type Output = Color;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        &self.pixels[y * self.width + x]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Image {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        &mut self.pixels[y * self.width + x]
+    }
+}
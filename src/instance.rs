@@ -0,0 +1,170 @@
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
+
+// Uniform scale, then rotation about x, y and z in turn, then translation.
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    translation: Vec3,
+    // Sine and cosine of the rotation about each axis.
+    rotation: [(f64, f64); 3],
+    scale: f64,
+}
+
+impl Transform {
+    // `rotation` holds angles in degrees.
+    pub fn new(translation: Vec3, rotation: Vec3, scale: f64) -> Self {
+        Self {
+            translation,
+            rotation: [
+                rotation.x().to_radians().sin_cos(),
+                rotation.y().to_radians().sin_cos(),
+                rotation.z().to_radians().sin_cos(),
+            ],
+            scale,
+        }
+    }
+
+    pub fn hit(&self, object: &dyn Hittable, r: &Ray, interval: Interval) -> Option<HitRecord> {
+        let mut record = object.hit(&self.local_ray(r), interval)?;
+        record.p = r.at(record.t);
+        record.normal = self.rotate(record.normal);
+        Some(record)
+    }
+
+    // Rotation and uniform scale preserve angles, so solid angle densities
+    // carry over from the object's own space unchanged.
+    pub fn pdf_value(&self, object: &dyn Hittable, r: &Ray) -> f64 {
+        object.pdf_value(&self.local_ray(r))
+    }
+
+    pub fn random(
+        &self,
+        object: &dyn Hittable,
+        origin: Point3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Vec3 {
+        let local_origin = self.inverse_rotate(origin - self.translation) / self.scale;
+        self.scale * self.rotate(object.random(local_origin, time, sampler))
+    }
+
+    fn local_ray(&self, r: &Ray) -> Ray {
+        let origin = self.inverse_rotate(r.origin() - self.translation) / self.scale;
+        let direction = self.inverse_rotate(r.direction()) / self.scale;
+        Ray::new(origin, direction, r.time())
+    }
+
+    fn rotate(&self, v: Vec3) -> Vec3 {
+        let [x, y, z] = self.rotation;
+        let v = rotate_axis(v, 1, 2, x);
+        let v = rotate_axis(v, 2, 0, y);
+        rotate_axis(v, 0, 1, z)
+    }
+
+    fn inverse_rotate(&self, v: Vec3) -> Vec3 {
+        let [(sin_x, cos_x), (sin_y, cos_y), (sin_z, cos_z)] = self.rotation;
+        let v = rotate_axis(v, 0, 1, (-sin_z, cos_z));
+        let v = rotate_axis(v, 2, 0, (-sin_y, cos_y));
+        rotate_axis(v, 1, 2, (-sin_x, cos_x))
+    }
+}
+
+// An object placed in the scene by a fixed transform.
+pub struct Instance {
+    object: Box<dyn Hittable>,
+    transform: Transform,
+}
+
+impl Instance {
+    // Rotates the object about x, y and z in turn, in degrees, then moves it
+    // by `translation`.
+    pub fn new(object: Box<dyn Hittable>, translation: Vec3, rotation: Vec3) -> Self {
+        Self {
+            object,
+            transform: Transform::new(translation, rotation, 1.0),
+        }
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, interval: Interval) -> Option<HitRecord> {
+        self.transform.hit(self.object.as_ref(), r, interval)
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        self.transform.pdf_value(self.object.as_ref(), r)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        self.transform
+            .random(self.object.as_ref(), origin, time, sampler)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+}
+
+fn rotate_axis(v: Vec3, a: usize, b: usize, (sin, cos): (f64, f64)) -> Vec3 {
+    let mut result = v;
+    result[a] = cos * v[a] - sin * v[b];
+    result[b] = sin * v[a] + cos * v[b];
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::color::Color;
+    use crate::material::DiffuseLight;
+    use crate::quad::Quad;
+    use crate::sampler::IndependentSampler;
+
+    fn light_quad(q: Point3, u: Vec3, v: Vec3) -> Quad {
+        Quad::new(
+            q,
+            u,
+            v,
+            Some(Arc::new(DiffuseLight::new(Color::new(1.0, 1.0, 1.0)))),
+        )
+    }
+
+    #[test]
+    fn instance_matches_the_moved_object() {
+        let (u, v) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let offset = Vec3::new(0.5, 2.0, -0.5);
+        let instance = Instance::new(
+            Box::new(light_quad(Point3::default(), u, v)),
+            offset,
+            Vec3::new(0.0, 90.0, 0.0),
+        );
+        // Turning +x by 90 degrees about y gives -z, and +z gives +x.
+        let moved = light_quad(offset, Vec3::new(0.0, 0.0, -1.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(instance.is_emissive());
+
+        let origin = Point3::new(0.2, 0.0, 0.1);
+        let mut sampler = IndependentSampler::new(7);
+        for _ in 0..32 {
+            let direction = instance.random(origin, 0.0, &mut sampler);
+            let ray = Ray::new(origin, direction, 0.0);
+            let pdf = instance.pdf_value(&ray);
+            assert!(pdf > 0.0);
+            assert!((pdf - moved.pdf_value(&ray)).abs() < 1e-9 * pdf);
+
+            let hit = |object: &dyn Hittable| {
+                object
+                    .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                    .unwrap()
+            };
+            let (a, b) = (hit(&instance), hit(&moved));
+            assert!((a.t - b.t).abs() < 1e-9);
+            assert!((a.p - b.p).length() < 1e-9);
+            assert!((a.normal - b.normal).length() < 1e-9);
+        }
+    }
+}
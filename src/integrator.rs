@@ -0,0 +1,337 @@
+use std::str::FromStr;
+
+use crate::background::Background;
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+// What an integrator sees of the scene. `lights` holds the emitters that are
+// sampled explicitly; they must also be part of `world` to be seen.
+pub struct Scene<'a> {
+    pub world: &'a dyn Hittable,
+    pub lights: &'a HittableList,
+    pub background: &'a Background,
+}
+
+pub trait Integrator: Send + Sync {
+    // Radiance arriving at the origin of `ray` from along its direction.
+    fn radiance(&self, ray: Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum IntegratorKind {
+    Path,
+    #[default]
+    PathNee,
+    Normals,
+    Depth,
+    Albedo,
+    AmbientOcclusion {
+        distance: f64,
+    },
+}
+
+impl IntegratorKind {
+    pub fn create(self, max_depth: i32, roulette_depth: i32) -> Box<dyn Integrator> {
+        match self {
+            IntegratorKind::Path => Box::new(PathTracer {
+                max_depth,
+                roulette_depth,
+                next_event: false,
+            }),
+            IntegratorKind::PathNee => Box::new(PathTracer {
+                max_depth,
+                roulette_depth,
+                next_event: true,
+            }),
+            IntegratorKind::Normals => Box::new(Normals),
+            IntegratorKind::Depth => Box::new(Depth),
+            IntegratorKind::Albedo => Box::new(Albedo),
+            IntegratorKind::AmbientOcclusion { distance } => {
+                Box::new(AmbientOcclusion { distance })
+            }
+        }
+    }
+
+    // Whether the output is data rather than a picture, and so shouldn't be
+    // tone mapped or encoded for display.
+    pub fn is_data(self) -> bool {
+        matches!(
+            self,
+            IntegratorKind::Normals
+                | IntegratorKind::Depth
+                | IntegratorKind::AmbientOcclusion { .. }
+        )
+    }
+}
+
+impl FromStr for IntegratorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(IntegratorKind::Path),
+            "nee" => Ok(IntegratorKind::PathNee),
+            "normals" => Ok(IntegratorKind::Normals),
+            "depth" => Ok(IntegratorKind::Depth),
+            "albedo" => Ok(IntegratorKind::Albedo),
+            "ao" => Ok(IntegratorKind::AmbientOcclusion {
+                distance: f64::INFINITY,
+            }),
+            _ => Err(format!("unknown integrator: {}", s)),
+        }
+    }
+}
+
+// `max_depth` only caps runaway paths; from `roulette_depth` bounces on,
+// paths are terminated with probability based on their throughput and
+// survivors are reweighted to keep the estimate unbiased.
+// With `next_event` set, non-specular hits also sample the lights and the
+// background directly, and both strategies are combined with multiple
+// importance sampling. Without it, lights are only found by scattered rays
+// and the sun is never seen.
+pub struct PathTracer {
+    pub max_depth: i32,
+    pub roulette_depth: i32,
+    pub next_event: bool,
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, r: Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        let mut ray = r;
+        let mut radiance = Color::default();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut scattering_pdf = 0.0;
+        for bounce in 0..self.max_depth {
+            let Some(record) = scene.world.hit(&ray, Interval::new(0.001, f64::INFINITY)) else {
+                let weight = if scattering_pdf > 0.0 {
+                    power_heuristic(scattering_pdf, scene.background.pdf(ray.direction()))
+                } else {
+                    1.0
+                };
+                radiance += weight * throughput * scene.background.evaluate(ray.direction());
+                break;
+            };
+            let Some(mat) = record.mat.as_ref() else {
+                break;
+            };
+
+            let emitted = mat.emitted(&ray, &record);
+            if emitted.max_component() > 0.0 {
+                let weight = if scattering_pdf > 0.0 && !scene.lights.is_empty() {
+                    power_heuristic(scattering_pdf, scene.lights.pdf_value(&ray))
+                } else {
+                    1.0
+                };
+                radiance += weight * throughput * emitted;
+            }
+            let Some(sample) = mat.sample(&ray, &record, sampler) else {
+                break;
+            };
+
+            scattering_pdf = if sample.delta || !self.next_event {
+                0.0
+            } else {
+                sample.pdf
+            };
+            if scattering_pdf > 0.0 {
+                radiance +=
+                    throughput * direct_lighting(&ray, &record, mat.as_ref(), scene, sampler);
+            }
+
+            throughput = throughput * sample.f / sample.pdf;
+            if bounce >= self.roulette_depth {
+                let survival = throughput.max_component().min(0.95);
+                if survival < 1.0 && sampler.get_1d() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = Ray::new(record.p, sample.direction, ray.time());
+        }
+
+        radiance
+    }
+}
+
+// Light arriving straight from the emitters and the background at a
+// non-specular hit, scattered back along the incoming ray.
+fn direct_lighting(
+    ray: &Ray,
+    record: &HitRecord,
+    mat: &dyn Material,
+    scene: &Scene,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    let mut lighting = Color::default();
+    if !scene.lights.is_empty() {
+        let direction = scene.lights.random(record.p, ray.time(), sampler);
+        let shadow = Ray::new(record.p, direction, ray.time());
+        let light_pdf = scene.lights.pdf_value(&shadow);
+        let scattering_pdf = mat.pdf(ray, record, direction);
+        if light_pdf > 0.0 && scattering_pdf > 0.0 {
+            let emitted = scene
+                .world
+                .hit(&shadow, Interval::new(0.001, f64::INFINITY))
+                .and_then(|hit| Some(hit.mat.as_ref()?.emitted(&shadow, &hit)));
+            if let Some(emitted) = emitted {
+                let weight = power_heuristic(light_pdf, scattering_pdf);
+                lighting += (weight / light_pdf) * mat.eval(ray, record, direction) * emitted;
+            }
+        }
+    }
+    if let Some((direction, light, light_pdf)) = scene.background.sample(sampler) {
+        let shadow = Ray::new(record.p, direction, ray.time());
+        let scattering_pdf = mat.pdf(ray, record, direction);
+        if scattering_pdf > 0.0 && !occluded(&shadow, scene.world, f64::INFINITY) {
+            let weight = power_heuristic(light_pdf, scattering_pdf);
+            lighting += (weight / light_pdf) * mat.eval(ray, record, direction) * light;
+        }
+    }
+    if let Some((direction, irradiance)) = scene.background.sun() {
+        let shadow = Ray::new(record.p, direction, ray.time());
+        let f = mat.eval(ray, record, direction);
+        if f.max_component() > 0.0 && !occluded(&shadow, scene.world, f64::INFINITY) {
+            lighting += f * irradiance;
+        }
+    }
+    lighting
+}
+
+// Shading normal mapped from [-1, 1] to [0, 1], flipped to face the ray.
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn radiance(&self, ray: Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Color {
+        match scene.world.hit(&ray, Interval::new(0.001, f64::INFINITY)) {
+            Some(record) => 0.5 * (record.normal + Color::new(1.0, 1.0, 1.0)),
+            None => Color::default(),
+        }
+    }
+}
+
+// Distance from the ray origin to the first hit, in scene units; misses are
+// zero.
+pub struct Depth;
+
+impl Integrator for Depth {
+    fn radiance(&self, ray: Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Color {
+        match scene.world.hit(&ray, Interval::new(0.001, f64::INFINITY)) {
+            Some(record) => {
+                let distance = record.t * ray.direction().length();
+                Color::new(distance, distance, distance)
+            }
+            None => Color::default(),
+        }
+    }
+}
+
+// Fraction of light a surface reflects towards the ray, estimated from one
+// BSDF sample per camera sample.
+pub struct Albedo;
+
+impl Integrator for Albedo {
+    fn radiance(&self, ray: Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        scene
+            .world
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .and_then(|record| record.mat.as_ref()?.sample(&ray, &record, sampler))
+            .map_or(Color::default(), |sample| sample.f / sample.pdf)
+    }
+}
+
+// Cosine-weighted share of the hemisphere above a hit that is unoccluded
+// within `distance`.
+pub struct AmbientOcclusion {
+    pub distance: f64,
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, ray: Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        let Some(record) = scene.world.hit(&ray, Interval::new(0.001, f64::INFINITY)) else {
+            return Color::default();
+        };
+        let mut direction = record.normal + Vec3::unit_random(sampler);
+        if direction.near_zero() {
+            direction = record.normal;
+        }
+
+        let probe = Ray::new(record.p, direction.unit(), ray.time());
+        if occluded(&probe, scene.world, self.distance) {
+            Color::default()
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+}
+
+fn occluded(shadow: &Ray, world: &dyn Hittable, distance: f64) -> bool {
+    world.hit(shadow, Interval::new(0.001, distance)).is_some()
+}
+
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    a / (a + b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::material::{DiffuseLight, Lambertian};
+    use crate::quad::Quad;
+    use crate::sampler::SamplerKind;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn next_event_estimation_matches_plain_path_tracing() {
+        let mut world = HittableList::default();
+        let white = Arc::new(Lambertian::new(Color::new(0.7, 0.7, 0.7)));
+        world.add(Box::new(Quad::new(
+            Point3::new(-5.0, 0.0, -5.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 10.0),
+            Some(white),
+        )));
+        let light = Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0)));
+        world.add(Box::new(Quad::new(
+            Point3::new(-1.0, 2.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            Some(light),
+        )));
+        let lights = world.lights();
+        let background = Background::Solid(Color::default());
+        let scene = Scene {
+            world: &world,
+            lights: &lights,
+            background: &background,
+        };
+
+        let mean_radiance = |next_event| {
+            let integrator = PathTracer {
+                max_depth: 5,
+                roulette_depth: 5,
+                next_event,
+            };
+            let mut sampler = SamplerKind::Independent.create(3, 1);
+            let count = 100_000;
+            let mut total = 0.0;
+            for i in 0..count {
+                sampler.start_pixel_sample(0, 0, i);
+                let ray = Ray::new(Point3::new(0.0, 1.0, 3.0), Vec3::new(0.0, -1.0, -1.0), 0.0);
+                total += integrator.radiance(ray, &scene, sampler.as_mut()).x();
+            }
+            total / count as f64
+        };
+        let (path, nee) = (mean_radiance(false), mean_radiance(true));
+        assert!(nee > 0.0);
+        assert!((path / nee - 1.0).abs() < 0.03, "{} {}", path, nee);
+    }
+}
@@ -0,0 +1,372 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::ppm::invalid_data;
+use crate::projection::{Frame, Projection};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
+
+// Double-Gauss 50mm f/2 (US patent 2,673,491), scaled from 100mm.
+const DOUBLE_GAUSS: &str = "
+# radius  thickness  ior    aperture
+29.475    3.76       1.67   25.2
+84.83     0.12       1      25.2
+19.275    4.025      1.67   23
+40.77     3.275      1.699  23
+12.75     5.705      1      18
+0         4.5        0      17.1
+-14.495   1.18       1.603  17
+40.77     6.065      1.658  20
+-20.385   0.19       1      20
+437.065   3.22       1.717  20
+-39.73    0          1      20
+";
+
+// Surfaces are listed from the scene side towards the film, in millimetres.
+// A zero curvature radius marks the aperture stop.
+#[derive(Clone, Copy, Debug)]
+struct LensInterface {
+    curvature_radius: f64,
+    thickness: f64,
+    eta: f64,
+    aperture_radius: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct LensSystem {
+    interfaces: Vec<LensInterface>,
+}
+
+impl Default for LensSystem {
+    fn default() -> Self {
+        Self::parse(&mut DOUBLE_GAUSS.as_bytes()).unwrap()
+    }
+}
+
+impl LensSystem {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<LensSystem> {
+        Self::parse(&mut BufReader::new(File::open(path)?))
+    }
+
+    // One surface per line: curvature radius, thickness, index of refraction
+    // and aperture diameter. Text after `#` is ignored.
+    pub fn parse(input: &mut impl BufRead) -> io::Result<LensSystem> {
+        let mut interfaces = Vec::new();
+        for line in input.lines() {
+            let line = line?;
+            let line = line.split('#').next().unwrap_or_default();
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let values = line
+                .split_whitespace()
+                .map(|token| {
+                    token
+                        .parse::<f64>()
+                        .map_err(|_| invalid_data(format!("invalid lens value: {}", token)))
+                })
+                .collect::<io::Result<Vec<f64>>>()?;
+            let [curvature_radius, thickness, eta, aperture] = values[..] else {
+                return Err(invalid_data(format!("expected 4 lens values: {}", line)));
+            };
+            interfaces.push(LensInterface {
+                curvature_radius: curvature_radius * 1e-3,
+                thickness: thickness * 1e-3,
+                eta,
+                aperture_radius: aperture * 0.5e-3,
+            });
+        }
+
+        if interfaces.is_empty() {
+            return Err(invalid_data(
+                "lens prescription has no surfaces".to_string(),
+            ));
+        }
+        Ok(LensSystem { interfaces })
+    }
+}
+
+// Traces rays from the film through every lens surface. Lens space has the
+// film at z = 0 and the scene towards negative z, matching the camera frame.
+#[derive(Debug)]
+pub struct Realistic {
+    frame: Frame,
+    interfaces: Vec<LensInterface>,
+    film_center: (f64, f64),
+    pixel_size: f64,
+}
+
+impl Realistic {
+    // The film is sized so the effective focal length gives `vfov`, and the
+    // lens is moved away from the film until `focus_distance` is sharp. Fails
+    // for prescriptions that block paraxial light and for focus distances
+    // closer than the lens can reach.
+    pub fn new(
+        frame: Frame,
+        image_width: i32,
+        image_height: i32,
+        vfov: f64,
+        focus_distance: f64,
+        lens: &LensSystem,
+    ) -> io::Result<Self> {
+        let mut realistic = Self {
+            frame,
+            interfaces: lens.interfaces.clone(),
+            film_center: (0.5 * image_width as f64, 0.5 * image_height as f64),
+            pixel_size: 0.0,
+        };
+
+        let (pz, fz) = realistic.thick_lens_approximation()?;
+        let focal_length = fz[0] - pz[0];
+        let z = -focus_distance;
+        let c = (pz[1] - z - pz[0]) * (pz[1] - z - 4.0 * focal_length - pz[0]);
+        if c <= 0.0 {
+            return Err(invalid_data(format!(
+                "lens can't focus at {}m with a {:.1}mm focal length",
+                focus_distance,
+                focal_length * 1e3
+            )));
+        }
+        let delta = 0.5 * (pz[1] - z + pz[0] - c.sqrt());
+        realistic.interfaces.last_mut().unwrap().thickness += delta;
+
+        let film_height = 2.0 * focal_length * (vfov.to_radians() / 2.0).tan();
+        realistic.pixel_size = film_height / image_height as f64;
+        Ok(realistic)
+    }
+
+    fn rear_z(&self) -> f64 {
+        self.interfaces.last().unwrap().thickness
+    }
+
+    fn front_z(&self) -> f64 {
+        self.interfaces
+            .iter()
+            .map(|interface| interface.thickness)
+            .sum()
+    }
+
+    // Principal plane and focal point positions for rays entering from the
+    // scene side ([0]) and from the film side ([1]).
+    fn thick_lens_approximation(&self) -> io::Result<([f64; 2], [f64; 2])> {
+        let height = 0.1
+            * self
+                .interfaces
+                .iter()
+                .map(|interface| interface.aperture_radius)
+                .fold(f64::INFINITY, f64::min);
+
+        let scene_ray = (
+            Point3::new(height, 0.0, -self.front_z() - 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        let film_ray = (
+            Point3::new(height, 0.0, 1.0 - self.rear_z()),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        let blocked = || invalid_data("paraxial ray blocked by lens system".to_string());
+        let from_scene = self
+            .trace_from_scene(scene_ray.0, scene_ray.1)
+            .ok_or_else(blocked)?;
+        let from_film = self
+            .trace_from_film(film_ray.0, film_ray.1)
+            .ok_or_else(blocked)?;
+
+        let (pz0, fz0) = cardinal_points(scene_ray.0, from_scene);
+        let (pz1, fz1) = cardinal_points(film_ray.0, from_film);
+        Ok(([pz0, pz1], [fz0, fz1]))
+    }
+
+    fn trace_from_film(&self, origin: Point3, direction: Vec3) -> Option<(Point3, Vec3)> {
+        let (mut origin, mut direction) = (origin, direction);
+        let mut element_z = 0.0;
+        for (i, interface) in self.interfaces.iter().enumerate().rev() {
+            element_z -= interface.thickness;
+            let (p, normal) = intersect_interface(interface, element_z, origin, direction)?;
+            origin = p;
+
+            if let Some(normal) = normal {
+                let eta_i = interface.eta;
+                let eta_t = match i {
+                    0 => 1.0,
+                    _ => air_if_zero(self.interfaces[i - 1].eta),
+                };
+                direction = refract(direction, normal, eta_i / eta_t)?;
+            }
+        }
+        Some((origin, direction))
+    }
+
+    fn trace_from_scene(&self, origin: Point3, direction: Vec3) -> Option<(Point3, Vec3)> {
+        let (mut origin, mut direction) = (origin, direction);
+        let mut element_z = -self.front_z();
+        for (i, interface) in self.interfaces.iter().enumerate() {
+            let (p, normal) = intersect_interface(interface, element_z, origin, direction)?;
+            origin = p;
+
+            if let Some(normal) = normal {
+                let eta_i = match i {
+                    0 => 1.0,
+                    _ => air_if_zero(self.interfaces[i - 1].eta),
+                };
+                let eta_t = air_if_zero(interface.eta);
+                direction = refract(direction, normal, eta_i / eta_t)?;
+            }
+            element_z += interface.thickness;
+        }
+        Some((origin, direction))
+    }
+}
+
+impl Projection for Realistic {
+    // Samples the rear element uniformly; rays blocked inside the lens are
+    // dropped, which produces the lens's natural vignetting.
+    fn generate_ray(
+        &self,
+        film_x: f64,
+        film_y: f64,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Ray> {
+        let (cx, cy) = self.film_center;
+        // The lens inverts the image, so the film is mirrored in both axes.
+        let film_point = Point3::new(
+            (cx - film_x) * self.pixel_size,
+            (film_y - cy) * self.pixel_size,
+            0.0,
+        );
+
+        let rear = self.interfaces.last().unwrap();
+        let disk = Vec3::random_in_unit_disk(sampler);
+        let rear_point = Point3::new(
+            rear.aperture_radius * disk.x(),
+            rear.aperture_radius * disk.y(),
+            -self.rear_z(),
+        );
+
+        let (origin, direction) = self.trace_from_film(film_point, rear_point - film_point)?;
+        Some(Ray::new(
+            self.frame.origin + self.frame.to_world(origin.x(), origin.y(), origin.z()),
+            self.frame
+                .to_world(direction.x(), direction.y(), direction.z()),
+            time,
+        ))
+    }
+}
+
+fn air_if_zero(eta: f64) -> f64 {
+    if eta == 0.0 {
+        1.0
+    } else {
+        eta
+    }
+}
+
+// Returns the hit point and, for refracting surfaces, the normal facing the
+// incoming ray.
+fn intersect_interface(
+    interface: &LensInterface,
+    element_z: f64,
+    origin: Point3,
+    direction: Vec3,
+) -> Option<(Point3, Option<Vec3>)> {
+    let radius = interface.curvature_radius;
+    let (t, normal) = if radius == 0.0 {
+        let t = (element_z - origin.z()) / direction.z();
+        if !t.is_finite() || t < 0.0 {
+            return None;
+        }
+        (t, None)
+    } else {
+        let center = Point3::new(0.0, 0.0, element_z + radius);
+        let oc = origin - center;
+        let a = direction.length_squared();
+        let half_b = oc.dot(direction);
+        let c = oc.length_squared() - radius * radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        let (t0, t1) = ((-half_b - sqrtd) / a, (-half_b + sqrtd) / a);
+        let t = if (direction.z() > 0.0) ^ (radius < 0.0) {
+            t0.min(t1)
+        } else {
+            t0.max(t1)
+        };
+        if t < 0.0 {
+            return None;
+        }
+
+        let normal = (oc + t * direction).unit();
+        let normal = if normal.dot(direction) > 0.0 {
+            -normal
+        } else {
+            normal
+        };
+        (t, Some(normal))
+    };
+
+    let p = origin + t * direction;
+    if p.x() * p.x() + p.y() * p.y() > interface.aperture_radius * interface.aperture_radius {
+        return None;
+    }
+    Some((p, normal))
+}
+
+fn refract(direction: Vec3, normal: Vec3, eta: f64) -> Option<Vec3> {
+    let unit_direction = direction.unit();
+    let cos_theta = (-unit_direction).dot(normal).min(1.0);
+    if eta * eta * (1.0 - cos_theta * cos_theta) > 1.0 {
+        return None;
+    }
+    Some(unit_direction.refract(normal, eta))
+}
+
+// Where a ray parallel to the axis at `input` crosses its own height again
+// (principal plane) and the axis (focal point) after leaving the lens.
+fn cardinal_points(input: Point3, (origin, direction): (Point3, Vec3)) -> (f64, f64) {
+    let tf = -origin.x() / direction.x();
+    let tp = (input.x() - origin.x()) / direction.x();
+    (
+        origin.z() + tp * direction.z(),
+        origin.z() + tf * direction.z(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn realistic(focus_distance: f64) -> io::Result<Realistic> {
+        Realistic::new(
+            Frame::default(),
+            64,
+            64,
+            20.0,
+            focus_distance,
+            &LensSystem::default(),
+        )
+    }
+
+    #[test]
+    fn focuses_at_distances_the_lens_reaches() {
+        assert!(realistic(10.0).is_ok());
+        assert!(realistic(0.5).is_ok());
+    }
+
+    #[test]
+    fn rejects_focus_closer_than_the_lens_reaches() {
+        assert!(realistic(0.01).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_prescriptions() {
+        assert!(LensSystem::parse(&mut "".as_bytes()).is_err());
+        assert!(LensSystem::parse(&mut "10 1 1.5".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,30 @@
+pub mod animation;
+pub mod aperture;
+pub mod background;
+pub mod camera;
+pub mod color;
+pub mod distribution;
+pub mod environment;
+pub mod exr;
+pub mod film;
+pub mod filter;
+pub mod hdr;
+pub mod hittable;
+pub mod hittable_list;
+pub mod image;
+pub mod instance;
+pub mod integrator;
+pub mod interval;
+pub mod lens;
+pub mod material;
+pub mod pfm;
+pub mod png;
+pub mod ppm;
+pub mod projection;
+pub mod quad;
+pub mod ray;
+pub mod sampler;
+pub mod sky;
+pub mod sphere;
+pub mod tonemap;
+pub mod vec3;
@@ -1,36 +1,436 @@
-use std::rc::Rc;
+use std::env;
+use std::f64::consts::PI;
+use std::io::{self, BufWriter, Write};
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Instant;
 
-use camera::Camera;
-use color::Color;
-use hittable_list::HittableList;
-use material::{Dielectric, Lambertian, Metal};
-use rand::Rng;
-use sphere::Sphere;
-use vec3::{Point3, Vec3};
-
-mod camera;
-mod color;
-mod hittable;
-mod hittable_list;
-mod interval;
-mod material;
-mod ray;
-mod sphere;
-mod vec3;
-
-fn main() {
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use ray_tracing::animation::{self, Animated, CameraAnimation, Interpolation, Track};
+use ray_tracing::aperture::{Aperture, ApertureShape};
+use ray_tracing::background::Background;
+use ray_tracing::camera::{AdaptiveSampling, Camera, CropWindow, FocusMode};
+use ray_tracing::color::Color;
+use ray_tracing::environment::EnvironmentMap;
+use ray_tracing::exr::PixelType;
+use ray_tracing::filter::Filter;
+use ray_tracing::hittable_list::HittableList;
+use ray_tracing::image::{self, Image, SaveOptions};
+use ray_tracing::instance::Instance;
+use ray_tracing::integrator::IntegratorKind;
+use ray_tracing::lens::LensSystem;
+use ray_tracing::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use ray_tracing::png::BitDepth;
+use ray_tracing::ppm;
+use ray_tracing::projection::ProjectionKind;
+use ray_tracing::quad::{self, Quad};
+use ray_tracing::sampler::SamplerKind;
+use ray_tracing::sky::Sky;
+use ray_tracing::sphere::Sphere;
+use ray_tracing::tonemap::DisplayTransform;
+use ray_tracing::vec3::{Point3, Vec3};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Scene {
+    #[default]
+    Spheres,
+    CornellBox,
+}
+
+impl FromStr for Scene {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spheres" => Ok(Scene::Spheres),
+            "cornell" => Ok(Scene::CornellBox),
+            _ => Err(format!("unknown scene: {}", s)),
+        }
+    }
+}
+
+struct Options {
+    output: Option<String>,
+    scene: Scene,
+    seed: u64,
+    sampler: SamplerKind,
+    adaptive_threshold: Option<f64>,
+    heatmap: Option<String>,
+    filter: Filter,
+    filter_radius: Option<f64>,
+    display: DisplayTransform,
+    format: SaveOptions,
+    projection: ProjectionKind,
+    motion_blur: bool,
+    frames: Option<(i32, i32)>,
+    fps: f64,
+    aperture_blades: Option<u32>,
+    aperture_rotation: f64,
+    aperture_image: Option<String>,
+    cats_eye: f64,
+    lens: Option<String>,
+    focus: FocusMode,
+    crop: Option<CropWindow>,
+    crop_canvas: bool,
+    roulette_depth: Option<i32>,
+    integrator: IntegratorKind,
+    ao_distance: Option<f64>,
+    background: Option<Background>,
+    environment: Option<String>,
+    environment_rotation: f64,
+    environment_intensity: f64,
+    sky: bool,
+    sun_elevation: f64,
+    sun_azimuth: f64,
+    turbidity: f64,
+    sky_intensity: f64,
+}
+
+impl Options {
+    fn parse() -> io::Result<Options> {
+        let mut options = Options {
+            output: None,
+            scene: Scene::default(),
+            seed: 0,
+            sampler: SamplerKind::default(),
+            adaptive_threshold: None,
+            heatmap: None,
+            filter: Filter::default(),
+            filter_radius: None,
+            display: DisplayTransform::default(),
+            format: SaveOptions::default(),
+            projection: ProjectionKind::default(),
+            motion_blur: false,
+            frames: None,
+            fps: 24.0,
+            aperture_blades: None,
+            aperture_rotation: 0.0,
+            aperture_image: None,
+            cats_eye: 0.0,
+            lens: None,
+            focus: FocusMode::default(),
+            crop: None,
+            crop_canvas: false,
+            roulette_depth: None,
+            integrator: IntegratorKind::default(),
+            ao_distance: None,
+            background: None,
+            environment: None,
+            environment_rotation: 0.0,
+            environment_intensity: 1.0,
+            sky: false,
+            sun_elevation: 45.0,
+            sun_azimuth: 0.0,
+            turbidity: 3.0,
+            sky_intensity: 1.0,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scene" => options.scene = parse_value(&arg, args.next())?,
+                "--seed" => options.seed = parse_value(&arg, args.next())?,
+                "--sampler" => options.sampler = parse_value(&arg, args.next())?,
+                "--adaptive" => options.adaptive_threshold = Some(parse_value(&arg, args.next())?),
+                "--heatmap" => options.heatmap = Some(parse_value(&arg, args.next())?),
+                "--filter" => options.filter = parse_value(&arg, args.next())?,
+                "--filter-radius" => options.filter_radius = Some(parse_value(&arg, args.next())?),
+                "--exposure" => options.display.exposure = parse_value(&arg, args.next())?,
+                "--tonemap" => options.display.tone_map = parse_value(&arg, args.next())?,
+                "--transfer" => options.display.transfer = parse_value(&arg, args.next())?,
+                "--legacy-display" => options.display = DisplayTransform::LEGACY,
+                "--png-16" => options.format.bit_depth = BitDepth::Sixteen,
+                "--exr-float" => options.format.pixel_type = PixelType::Float,
+                "--alpha" => options.format.alpha = true,
+                "--projection" => options.projection = parse_value(&arg, args.next())?,
+                "--motion-blur" => options.motion_blur = true,
+                "--frames" => options.frames = Some(parse_frames(&arg, args.next())?),
+                "--fps" => options.fps = parse_value(&arg, args.next())?,
+                "--aperture-blades" => {
+                    options.aperture_blades = Some(parse_value(&arg, args.next())?)
+                }
+                "--aperture-rotation" => {
+                    options.aperture_rotation = parse_value(&arg, args.next())?
+                }
+                "--aperture-image" => {
+                    options.aperture_image = Some(parse_value(&arg, args.next())?)
+                }
+                "--cats-eye" => options.cats_eye = parse_value(&arg, args.next())?,
+                "--focus" => options.focus = parse_value(&arg, args.next())?,
+                "--crop" => options.crop = Some(parse_value(&arg, args.next())?),
+                "--crop-canvas" => options.crop_canvas = true,
+                "--roulette-depth" => {
+                    options.roulette_depth = Some(parse_value(&arg, args.next())?)
+                }
+                "--integrator" => options.integrator = parse_value(&arg, args.next())?,
+                "--ao-distance" => options.ao_distance = Some(parse_value(&arg, args.next())?),
+                "--background" => options.background = Some(parse_value(&arg, args.next())?),
+                "--environment" => options.environment = Some(parse_value(&arg, args.next())?),
+                "--environment-rotation" => {
+                    options.environment_rotation = parse_value(&arg, args.next())?
+                }
+                "--environment-intensity" => {
+                    options.environment_intensity = parse_value(&arg, args.next())?
+                }
+                "--sky" => options.sky = true,
+                "--sun-elevation" => options.sun_elevation = parse_value(&arg, args.next())?,
+                "--sun-azimuth" => options.sun_azimuth = parse_value(&arg, args.next())?,
+                "--turbidity" => options.turbidity = parse_value(&arg, args.next())?,
+                "--sky-intensity" => options.sky_intensity = parse_value(&arg, args.next())?,
+                "--lens" => {
+                    options.lens = Some(parse_value(&arg, args.next())?);
+                    options.projection = ProjectionKind::Realistic;
+                }
+                _ if arg.starts_with("--") => {
+                    return Err(invalid_argument(format!("unknown option: {}", arg)))
+                }
+                _ => options.output = Some(arg),
+            }
+        }
+
+        if let Some(ao_distance) = options.ao_distance {
+            match &mut options.integrator {
+                IntegratorKind::AmbientOcclusion { distance } => *distance = ao_distance,
+                _ => {
+                    return Err(invalid_argument(
+                        "--ao-distance needs --integrator ao".to_string(),
+                    ))
+                }
+            }
+        }
+
+        // The sun is a directional light that only next event estimation
+        // can find.
+        if options.sky && options.integrator == IntegratorKind::Path {
+            return Err(invalid_argument(
+                "--sky needs an integrator with next event estimation".to_string(),
+            ));
+        }
+
+        Ok(options)
+    }
+
+    fn aperture(&self) -> io::Result<Aperture> {
+        let shape = match (&self.aperture_image, self.aperture_blades) {
+            (Some(path), _) => ApertureShape::from_image(&Image::load(path)?),
+            (None, Some(blades)) => ApertureShape::Polygon {
+                blades,
+                rotation: self.aperture_rotation,
+            },
+            (None, None) => ApertureShape::Circle,
+        };
+        Ok(Aperture {
+            shape,
+            cats_eye: self.cats_eye,
+        })
+    }
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> io::Result<T> {
+    let value = value.ok_or_else(|| invalid_argument(format!("missing value for {}", flag)))?;
+    value
+        .parse()
+        .map_err(|_| invalid_argument(format!("invalid value for {}: {}", flag, value)))
+}
+
+fn parse_frames(flag: &str, value: Option<String>) -> io::Result<(i32, i32)> {
+    let value: String = parse_value(flag, value)?;
+    let (start, end) = value
+        .split_once("..")
+        .ok_or_else(|| invalid_argument(format!("expected START..END for {}", flag)))?;
+    Ok((
+        parse_value(flag, Some(start.to_string()))?,
+        parse_value(flag, Some(end.to_string()))?,
+    ))
+}
+
+fn invalid_argument(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn main() -> io::Result<()> {
+    let options = Options::parse()?;
+
+    let mut camera = Camera::default();
+    let world = match options.scene {
+        Scene::Spheres => random_spheres(
+            &mut camera,
+            options.seed,
+            options.motion_blur,
+            options.frames,
+            options.fps,
+        ),
+        Scene::CornellBox => cornell_box(&mut camera),
+    };
+    let lights = world.lights();
+
+    if let Some(depth) = options.roulette_depth {
+        camera.roulette_depth = depth;
+    }
+    if let Some(background) = &options.background {
+        camera.background = background.clone();
+    }
+    if options.sky {
+        camera.background = Background::Sky(Sky::new(
+            options.sun_elevation,
+            options.sun_azimuth,
+            options.turbidity,
+            options.sky_intensity,
+        ));
+    }
+    if let Some(path) = &options.environment {
+        camera.background = Background::Environment(Arc::new(EnvironmentMap::new(
+            Image::load(path)?,
+            options.environment_rotation,
+            options.environment_intensity,
+        )));
+    }
+    camera.alpha = options.format.alpha;
+    camera.focus = options.focus;
+    camera.aperture = options.aperture()?;
+    camera.seed = options.seed;
+    camera.sampler = options.sampler;
+    camera.integrator = options.integrator;
+    camera.adaptive = options
+        .adaptive_threshold
+        .map(|threshold| AdaptiveSampling {
+            min_samples: 16,
+            max_samples: camera.sample_per_pixel,
+            threshold,
+        });
+
+    camera.projection = options.projection;
+    if let Some(path) = &options.lens {
+        camera.lens = LensSystem::load(path)?;
+    }
+    if options.motion_blur {
+        camera.shutter_open = 0.0;
+        camera.shutter_close = 1.0;
+    }
+    camera.crop = options.crop.map(|crop| CropWindow {
+        full_canvas: options.crop_canvas,
+        ..crop
+    });
+    // The canvas outside the crop is only transparent with an alpha channel.
+    let mut save_options = options.format;
+    save_options.alpha |= options.crop_canvas;
+    let display = if options.integrator.is_data() {
+        DisplayTransform::LINEAR
+    } else {
+        options.display
+    };
+    // Depth stays in scene units in float formats. Other formats get it
+    // scaled by the farthest hit of the first frame, so every frame of a
+    // sequence shares one scale.
+    let scale_depth = options.integrator == IntegratorKind::Depth
+        && !options
+            .output
+            .as_deref()
+            .is_some_and(image::is_float_format);
+    let mut farthest = None;
+    camera.filter = match options.filter_radius {
+        Some(radius) => options.filter.with_radius(radius),
+        None => options.filter,
+    };
+
+    match options.frames {
+        Some((start, end)) => {
+            let pattern = options
+                .output
+                .ok_or_else(|| invalid_argument("--frames requires an output path".to_string()))?;
+            let turntable = turntable(
+                &camera,
+                animation::frame_time(start, options.fps),
+                animation::frame_time(end, options.fps),
+            );
+
+            for frame in start..end {
+                let time = animation::frame_time(frame, options.fps);
+                turntable.apply(&mut camera, time);
+                camera.shutter_open = time;
+                camera.shutter_close = if options.motion_blur {
+                    time + 0.5 / options.fps
+                } else {
+                    time
+                };
+
+                eprintln!("Frame {}", frame);
+                let now = Instant::now();
+                let mut image = camera.render(&world, &lights)?;
+                eprintln!("Rendered in {}s", now.elapsed().as_secs_f64());
+                if scale_depth {
+                    normalize_depth(&mut image, &mut farthest);
+                }
+
+                if let Some(heatmap) = &options.heatmap {
+                    camera.sample_count_heatmap().save(
+                        animation::frame_path(heatmap, frame),
+                        &DisplayTransform::LINEAR,
+                        save_options,
+                    )?;
+                }
+                image.save(
+                    animation::frame_path(&pattern, frame),
+                    &display,
+                    save_options,
+                )?;
+            }
+            Ok(())
+        }
+        None => {
+            let now = Instant::now();
+            let mut image = camera.render(&world, &lights)?;
+            eprintln!("Rendered in {}s", now.elapsed().as_secs_f64());
+            if scale_depth {
+                normalize_depth(&mut image, &mut farthest);
+            }
+
+            if let Some(path) = options.heatmap {
+                camera.sample_count_heatmap().save(
+                    path,
+                    &DisplayTransform::LINEAR,
+                    save_options,
+                )?;
+            }
+
+            match options.output {
+                Some(path) => image.save(path, &display, save_options),
+                None => {
+                    let mut out = BufWriter::new(io::stdout().lock());
+                    ppm::write_p3(&image, &mut out, &display)?;
+                    out.flush()
+                }
+            }
+        }
+    }
+}
+
+fn normalize_depth(image: &mut Image, farthest: &mut Option<f64>) {
+    let farthest = *farthest.get_or_insert_with(|| image.max_component());
+    if farthest > 0.0 {
+        image.scale(1.0 / farthest);
+    }
+}
+
+fn random_spheres(
+    camera: &mut Camera,
+    seed: u64,
+    motion_blur: bool,
+    frames: Option<(i32, i32)>,
+    fps: f64,
+) -> HittableList {
+    let mut rng = Pcg64Mcg::seed_from_u64(seed);
     let mut world = HittableList::default();
     let ground_material = Lambertian::new(Color::new(0.5, 0.5, 0.5));
     world.add(Box::new(Sphere::new(
         Point3::new(0.0, -1000.0, 0.0),
         1000.0,
-        Some(Rc::new(ground_material)),
+        Some(Arc::new(ground_material)),
     )));
 
     for a in -11..11 {
         for b in -11..11 {
-            let mut rng = rand::thread_rng();
             let choose_mat = rng.gen_range(0.0..=1.0);
             let center = Point3::new(
                 a as f64 + 0.9 * rng.gen_range(0.0..=1.0),
@@ -47,11 +447,23 @@ fn main() {
                     );
                     let albedo = albedo * albedo;
                     let sphere_material = Lambertian::new(albedo);
-                    world.add(Box::new(Sphere::new(
-                        center,
-                        0.2,
-                        Some(Rc::new(sphere_material)),
-                    )));
+                    if motion_blur {
+                        let center1 = center + Vec3::new(0.0, rng.gen_range(0.0..=0.5), 0.0);
+                        world.add(Box::new(Sphere::moving(
+                            center,
+                            center1,
+                            0.0,
+                            1.0,
+                            0.2,
+                            Some(Arc::new(sphere_material)),
+                        )));
+                    } else {
+                        world.add(Box::new(Sphere::new(
+                            center,
+                            0.2,
+                            Some(Arc::new(sphere_material)),
+                        )));
+                    }
                 } else if choose_mat < 0.95 {
                     let albedo = Color::new(
                         rng.gen_range(0.5..=1.0),
@@ -63,14 +475,14 @@ fn main() {
                     world.add(Box::new(Sphere::new(
                         center,
                         0.2,
-                        Some(Rc::new(sphere_material)),
+                        Some(Arc::new(sphere_material)),
                     )));
                 } else {
                     let sphere_material = Dielectric::new(1.5);
                     world.add(Box::new(Sphere::new(
                         center,
                         0.2,
-                        Some(Rc::new(sphere_material)),
+                        Some(Arc::new(sphere_material)),
                     )));
                 }
             }
@@ -78,25 +490,38 @@ fn main() {
     }
 
     let material1 = Dielectric::new(1.5);
-    world.add(Box::new(Sphere::new(
+    let glass_sphere = Box::new(Sphere::new(
         Point3::new(0.0, 1.0, 0.0),
         1.0,
-        Some(Rc::new(material1)),
-    )));
+        Some(Arc::new(material1)),
+    ));
+    match frames {
+        Some((start, end)) => {
+            let mut bobbing = Animated::new(glass_sphere);
+            let start_time = animation::frame_time(start, fps);
+            let end_time = animation::frame_time(end, fps);
+            let middle_time = 0.5 * (start_time + end_time);
+            bobbing.translation = Track::new(Interpolation::CatmullRom)
+                .key(start_time, Vec3::default())
+                .key(middle_time, Vec3::new(0.0, 0.5, 0.0))
+                .key(end_time, Vec3::default());
+            world.add(Box::new(bobbing));
+        }
+        None => world.add(glass_sphere),
+    }
     let material2 = Lambertian::new(Color::new(0.4, 0.2, 0.1));
     world.add(Box::new(Sphere::new(
         Point3::new(-4.0, 1.0, 0.0),
         1.0,
-        Some(Rc::new(material2)),
+        Some(Arc::new(material2)),
     )));
     let material3 = Metal::new(Color::new(0.7, 0.6, 0.5), 0.0);
     world.add(Box::new(Sphere::new(
         Point3::new(4.0, 1.0, 0.0),
         1.0,
-        Some(Rc::new(material3)),
+        Some(Arc::new(material3)),
     )));
 
-    let mut camera = Camera::default();
     camera.aspect_ratio = 16.0 / 9.0;
     camera.image_width = 1200;
     camera.sample_per_pixel = 500;
@@ -108,7 +533,114 @@ fn main() {
     camera.defocus_angle = 0.6;
     camera.focus_distance = 10.0;
 
-    let now = Instant::now();
-    camera.render(&world);
-    eprintln!("Rendered in {}s", now.elapsed().as_secs_f64());
+    world
+}
+
+fn cornell_box(camera: &mut Camera) -> HittableList {
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(Color::new(15.0, 15.0, 15.0)));
+
+    let mut world = HittableList::default();
+    let walls = [
+        (
+            Point3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            &green,
+        ),
+        (
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            &red,
+        ),
+        (
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            &white,
+        ),
+        (
+            Point3::new(555.0, 555.0, 555.0),
+            Vec3::new(-555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -555.0),
+            &white,
+        ),
+        (
+            Point3::new(0.0, 0.0, 555.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            &white,
+        ),
+    ];
+    for (q, u, v, material) in walls {
+        world.add(Box::new(Quad::new(q, u, v, Some(material.clone()))));
+    }
+
+    world.add(Box::new(Quad::new(
+        Point3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        Some(light),
+    )));
+
+    let blocks = [
+        (
+            Point3::new(165.0, 330.0, 165.0),
+            15.0,
+            Vec3::new(265.0, 0.0, 295.0),
+        ),
+        (
+            Point3::new(165.0, 165.0, 165.0),
+            -18.0,
+            Vec3::new(130.0, 0.0, 65.0),
+        ),
+    ];
+    for (size, angle, offset) in blocks {
+        let block = quad::cuboid(Point3::default(), size, Some(white.clone()));
+        world.add(Box::new(Instance::new(
+            Box::new(block),
+            offset,
+            Vec3::new(0.0, angle, 0.0),
+        )));
+    }
+
+    camera.aspect_ratio = 1.0;
+    camera.image_width = 600;
+    camera.sample_per_pixel = 200;
+    camera.max_depth = 50;
+    camera.background = Background::Solid(Color::default());
+    camera.vfov = 40.0;
+    camera.lookfrom = Point3::new(278.0, 278.0, -800.0);
+    camera.lookat = Point3::new(278.0, 278.0, 0.0);
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    camera.focus_distance = 800.0;
+
+    world
+}
+
+fn turntable(camera: &Camera, start_time: f64, end_time: f64) -> CameraAnimation {
+    const KEYS: i32 = 8;
+
+    let offset = camera.lookfrom - camera.lookat;
+    let radius = (offset.x() * offset.x() + offset.z() * offset.z()).sqrt();
+    let angle0 = offset.z().atan2(offset.x());
+
+    let mut lookfrom = Track::new(Interpolation::CatmullRom);
+    for k in 0..=KEYS {
+        let s = k as f64 / KEYS as f64;
+        let angle = angle0 + s * 2.0 * PI;
+        lookfrom.add_key(
+            start_time + s * (end_time - start_time),
+            camera.lookat + Vec3::new(radius * angle.cos(), offset.y(), radius * angle.sin()),
+        );
+    }
+
+    CameraAnimation {
+        lookfrom,
+        ..CameraAnimation::default()
+    }
 }
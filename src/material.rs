@@ -1,12 +1,54 @@
-use rand::Rng;
+use std::f64::consts::PI;
 
 use crate::color::Color;
 use crate::hittable::HitRecord;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 
-pub trait Material {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)>;
+// A direction drawn from a material's BSDF. `f` already includes the cosine
+// term, so a path's throughput is scaled by `f / pdf`. Delta lobes can't be
+// evaluated or hit by any other strategy; they report `f` and `pdf` with the
+// shared Dirac delta divided out.
+#[derive(Clone, Copy, Debug)]
+pub struct BsdfSample {
+    pub direction: Vec3,
+    pub f: Color,
+    pub pdf: f64,
+    pub delta: bool,
+}
+
+pub trait Material: Send + Sync {
+    // Directions are sampled from the hit point; `None` means the path is
+    // absorbed.
+    fn sample(
+        &self,
+        _ray_in: &Ray,
+        _hit_record: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<BsdfSample> {
+        None
+    }
+
+    // The BSDF times the cosine term for scattering towards `direction`. Delta
+    // lobes contribute nothing here.
+    fn eval(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: Vec3) -> Color {
+        Color::default()
+    }
+
+    // Solid angle density of `sample` producing `direction`, excluding delta
+    // lobes.
+    fn pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    fn emitted(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
+        Color::default()
+    }
+
+    fn is_emissive(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Clone)]
@@ -21,13 +63,35 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
-        let mut scatter_direction = hit_record.normal + Vec3::unit_random();
-        if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<BsdfSample> {
+        let mut direction = hit_record.normal + Vec3::unit_random(sampler);
+        if direction.near_zero() {
+            direction = hit_record.normal;
+        }
+        let pdf = self.pdf(ray_in, hit_record, direction);
+        if pdf <= 0.0 {
+            return None;
         }
-        let scattered = Ray::new(hit_record.p, scatter_direction);
-        Some((self.albedo, scattered))
+        Some(BsdfSample {
+            direction,
+            f: self.eval(ray_in, hit_record, direction),
+            pdf,
+            delta: false,
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, direction: Vec3) -> Color {
+        self.pdf(ray_in, hit_record, direction) * self.albedo
+    }
+
+    fn pdf(&self, _ray_in: &Ray, hit_record: &HitRecord, direction: Vec3) -> f64 {
+        let cosine = hit_record.normal.dot(direction.unit());
+        cosine.max(0.0) / PI
     }
 }
 
@@ -45,14 +109,58 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<BsdfSample> {
         let reflected = ray_in.direction().unit().reflect(hit_record.normal);
-        let scattered = Ray::new(hit_record.p, reflected + self.fuzz * Vec3::unit_random());
-        if scattered.direction().dot(hit_record.normal) > 0.0 {
-            Some((self.albedo, scattered))
-        } else {
-            None
+        let direction = reflected + self.fuzz * Vec3::unit_random(sampler);
+        if direction.dot(hit_record.normal) <= 0.0 {
+            return None;
+        }
+        if self.fuzz == 0.0 {
+            return Some(BsdfSample {
+                direction,
+                f: self.albedo,
+                pdf: 1.0,
+                delta: true,
+            });
+        }
+
+        let pdf = self.pdf(ray_in, hit_record, direction);
+        if !(pdf > 0.0 && pdf.is_finite()) {
+            return None;
+        }
+        Some(BsdfSample {
+            direction,
+            f: pdf * self.albedo,
+            pdf,
+            delta: false,
+        })
+    }
+
+    // Directions absorbed below the surface are lost, so the albedo is scaled
+    // by the density alone.
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, direction: Vec3) -> Color {
+        self.pdf(ray_in, hit_record, direction) * self.albedo
+    }
+
+    // Sampling offsets the unit mirror direction by a point on a sphere of
+    // radius `fuzz`, which a direction crosses at up to two distances.
+    fn pdf(&self, ray_in: &Ray, hit_record: &HitRecord, direction: Vec3) -> f64 {
+        let direction = direction.unit();
+        if self.fuzz == 0.0 || direction.dot(hit_record.normal) <= 0.0 {
+            return 0.0;
         }
+        let reflected = ray_in.direction().unit().reflect(hit_record.normal);
+        let b = direction.dot(reflected);
+        let discriminant = b * b - (1.0 - self.fuzz * self.fuzz);
+        if b <= 0.0 || discriminant <= 0.0 {
+            return 0.0;
+        }
+        (b * b + discriminant) / (2.0 * PI * self.fuzz * discriminant.sqrt())
     }
 }
 
@@ -73,7 +181,14 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+    // Reflection and refraction are picked in proportion to the Fresnel
+    // reflectance, which cancels it out of the throughput.
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<BsdfSample> {
         let refraction_ratio = if hit_record.front_face {
             1.0 / self.ir
         } else {
@@ -84,15 +199,163 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let mut rng = rand::thread_rng();
         let direction = if cannot_refract
-            || Self::reflectance(cos_theta, refraction_ratio) > rng.gen_range(0.0..=1.0)
+            || Self::reflectance(cos_theta, refraction_ratio) > sampler.get_1d()
         {
             unit_direction.reflect(hit_record.normal)
         } else {
             unit_direction.refract(hit_record.normal, refraction_ratio)
         };
 
-        return Some((Color::new(1.0, 1.0, 1.0), Ray::new(hit_record.p, direction)));
+        Some(BsdfSample {
+            direction,
+            f: Color::new(1.0, 1.0, 1.0),
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct DiffuseLight {
+    emit: Color,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
+        self.emit
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::SamplerKind;
+    use crate::vec3::Point3;
+
+    // A ray arriving at the origin on a surface facing +z, `angle` degrees
+    // from the normal.
+    fn incoming(angle: f64) -> (Ray, HitRecord) {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let ray = Ray::new(Point3::new(-sin, 0.0, cos), Vec3::new(sin, 0.0, -cos), 0.0);
+        let record = HitRecord::new(Point3::default(), Vec3::new(0.0, 0.0, 1.0), 1.0, &ray, None);
+        (ray, record)
+    }
+
+    fn samples(
+        material: &dyn Material,
+        ray: &Ray,
+        record: &HitRecord,
+        count: u32,
+    ) -> Vec<Option<BsdfSample>> {
+        let mut sampler = SamplerKind::Independent.create(7, count);
+        (0..count)
+            .map(|i| {
+                sampler.start_pixel_sample(0, 0, i);
+                material.sample(ray, record, sampler.as_mut())
+            })
+            .collect()
+    }
+
+    // Integral of the material's pdf over the hemisphere above the surface.
+    fn hemisphere_integral(material: &dyn Material, ray: &Ray, record: &HitRecord) -> f64 {
+        let n = 800;
+        let (d_theta, d_phi) = (0.5 * PI / n as f64, 2.0 * PI / n as f64);
+        let mut integral = 0.0;
+        for i in 0..n {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..n {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                integral += material.pdf(ray, record, direction) * theta.sin() * d_theta * d_phi;
+            }
+        }
+        integral
+    }
+
+    #[test]
+    fn samples_agree_with_eval_and_pdf() {
+        let materials: [&dyn Material; 3] = [
+            &Lambertian::new(Color::new(0.8, 0.4, 0.2)),
+            &Metal::new(Color::new(0.9, 0.6, 0.3), 0.3),
+            &Metal::new(Color::new(0.9, 0.6, 0.3), 0.9),
+        ];
+        for material in materials {
+            for angle in [0.0, 40.0, 80.0] {
+                let (ray, record) = incoming(angle);
+                for sample in samples(material, &ray, &record, 200).into_iter().flatten() {
+                    assert!(!sample.delta);
+                    let pdf = material.pdf(&ray, &record, sample.direction);
+                    let f = material.eval(&ray, &record, sample.direction);
+                    assert!((pdf / sample.pdf - 1.0).abs() < 1e-9);
+                    assert!((f.x() - sample.f.x()).abs() <= 1e-9 * f.x());
+                    assert!((f.z() - sample.f.z()).abs() <= 1e-9 * f.z());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pdfs_integrate_to_the_scattered_fraction() {
+        let (ray, record) = incoming(30.0);
+        let lambertian = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+        assert!((hemisphere_integral(&lambertian, &ray, &record) - 1.0).abs() < 1e-3);
+
+        // At grazing incidence part of the fuzz sphere lies below the surface,
+        // and those samples are absorbed.
+        for (angle, fuzz) in [(30.0, 0.5), (75.0, 0.5), (60.0, 1.0)] {
+            let (ray, record) = incoming(angle);
+            let metal = Metal::new(Color::new(0.5, 0.5, 0.5), fuzz);
+            let integral = hemisphere_integral(&metal, &ray, &record);
+            let count = 20000;
+            let scattered = samples(&metal, &ray, &record, count)
+                .iter()
+                .filter(|sample| sample.is_some())
+                .count() as f64
+                / count as f64;
+            assert!(integral <= 1.0 + 1e-2, "{}", integral);
+            assert!(
+                (integral - scattered).abs() < 2e-2,
+                "{} {}",
+                integral,
+                scattered
+            );
+        }
+    }
+
+    #[test]
+    fn specular_lobes_are_delta() {
+        let materials: [&dyn Material; 2] = [
+            &Dielectric::new(1.5),
+            &Metal::new(Color::new(0.9, 0.9, 0.9), 0.0),
+        ];
+        for material in materials {
+            let (ray, record) = incoming(40.0);
+            for sample in samples(material, &ray, &record, 20) {
+                let sample = sample.unwrap();
+                assert!(sample.delta);
+                assert_eq!(material.pdf(&ray, &record, sample.direction), 0.0);
+                assert_eq!(
+                    material
+                        .eval(&ray, &record, sample.direction)
+                        .max_component(),
+                    0.0
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,109 @@
+use std::io::{self, BufRead, Write};
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::ppm::{invalid_data, parse_token, pixel_count, read_token};
+
+pub fn write_pfm(image: &Image, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "PF")?;
+    writeln!(out, "{} {}", image.width(), image.height())?;
+    writeln!(out, "-1.0")?;
+
+    let mut data = Vec::with_capacity(image.pixels().len() * 12);
+    for y in (0..image.height()).rev() {
+        for x in 0..image.width() {
+            let pixel = image[(x, y)];
+            for c in [pixel.x(), pixel.y(), pixel.z()] {
+                data.extend((c as f32).to_le_bytes());
+            }
+        }
+    }
+    out.write_all(&data)
+}
+
+pub fn read_pfm(input: &mut impl BufRead) -> io::Result<Image> {
+    let magic = read_token(input)?;
+    let channels = match magic.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data(format!("unsupported PFM magic: {}", magic))),
+    };
+    let width = parse_token(input)?;
+    let height = parse_token(input)?;
+    // The sign of the scale gives the byte order and its magnitude multiplies
+    // every sample.
+    let scale: f64 = parse_token(input)?;
+    let little_endian = scale < 0.0;
+    if scale == 0.0 || !scale.is_finite() {
+        return Err(invalid_data(format!("invalid PFM scale: {}", scale)));
+    }
+
+    let mut data = vec![0; pixel_count(width, height)? * channels * 4];
+    input.read_exact(&mut data)?;
+    let samples: Vec<f64> = data
+        .chunks(4)
+        .map(|bytes| {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            let value = if little_endian {
+                f32::from_le_bytes(bytes)
+            } else {
+                f32::from_be_bytes(bytes)
+            };
+            value as f64 * scale.abs()
+        })
+        .collect();
+
+    let mut image = Image::new(width, height);
+    for (i, pixel) in samples.chunks(channels).enumerate() {
+        let color = match pixel {
+            [r, g, b] => Color::new(*r, *g, *b),
+            [v] => Color::new(*v, *v, *v),
+            _ => unreachable!(),
+        };
+        image[(i % width, height - 1 - i / width)] = color;
+    }
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_is_exact_for_f32_values() {
+        let mut image = Image::new(3, 2);
+        image[(0, 0)] = Color::new(0.5, 1e4, -3.25);
+        image[(2, 1)] = Color::new(1e-6, 0.0, 7.0);
+
+        let mut data = Vec::new();
+        write_pfm(&image, &mut data).unwrap();
+        let decoded = read_pfm(&mut data.as_slice()).unwrap();
+
+        assert_eq!((decoded.width(), decoded.height()), (3, 2));
+        for (expected, actual) in image.pixels().iter().zip(decoded.pixels()) {
+            for c in 0..3 {
+                assert_eq!(expected[c] as f32 as f64, actual[c]);
+            }
+        }
+    }
+
+    #[test]
+    fn applies_scale_magnitude_and_byte_order() {
+        let mut data = b"Pf\n2 1\n2.0\n".to_vec();
+        data.extend(0.5f32.to_be_bytes());
+        data.extend(3.0f32.to_be_bytes());
+
+        let image = read_pfm(&mut data.as_slice()).unwrap();
+        assert_eq!(image[(0, 0)].x(), 1.0);
+        assert_eq!(image[(1, 0)].z(), 6.0);
+    }
+
+    #[test]
+    fn rejects_oversized_header() {
+        let data = b"PF\n18446744073709551615 2\n-1.0\n".to_vec();
+        assert!(read_pfm(&mut data.as_slice()).is_err());
+        let data = b"PF\n100000 100000\n-1.0\n".to_vec();
+        assert!(read_pfm(&mut data.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::io::{self, Write};
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::tonemap::DisplayTransform;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    #[default]
+    Eight,
+    Sixteen,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PngOptions {
+    pub bit_depth: BitDepth,
+    pub alpha: bool,
+}
+
+pub fn write_png(
+    image: &Image,
+    out: &mut impl Write,
+    display: &DisplayTransform,
+    options: PngOptions,
+) -> io::Result<()> {
+    let mut encoder = ::png::Encoder::new(out, image.width() as u32, image.height() as u32);
+    encoder.set_color(if options.alpha {
+        ::png::ColorType::Rgba
+    } else {
+        ::png::ColorType::Rgb
+    });
+    encoder.set_depth(match options.bit_depth {
+        BitDepth::Eight => ::png::BitDepth::Eight,
+        BitDepth::Sixteen => ::png::BitDepth::Sixteen,
+    });
+
+    let mut data = Vec::new();
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            // PNG stores straight colour, so coverage is divided back out.
+            let coverage = image.alpha(x, y);
+            let color = if options.alpha && coverage > 0.0 {
+                image[(x, y)] / coverage
+            } else {
+                image[(x, y)]
+            };
+            let pixel = display.apply(color);
+            let alpha = Color::quantize(coverage, options.bit_depth.levels());
+            match options.bit_depth {
+                BitDepth::Eight => {
+                    data.extend(pixel.to_rgb8());
+                    if options.alpha {
+                        data.push(alpha as u8);
+                    }
+                }
+                BitDepth::Sixteen => {
+                    data.extend(pixel.to_rgb16().iter().flat_map(|c| c.to_be_bytes()));
+                    if options.alpha {
+                        data.extend((alpha as u16).to_be_bytes());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+impl BitDepth {
+    fn levels(self) -> f64 {
+        match self {
+            BitDepth::Eight => 256.0,
+            BitDepth::Sixteen => 65536.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alpha_output_divides_out_coverage() {
+        let mut image = Image::new(2, 1);
+        image[(0, 0)] = Color::new(0.125, 0.125, 0.125);
+        image.set_alpha(0, 0, 0.5);
+        image[(1, 0)] = Color::new(1.0, 1.0, 1.0);
+        image.set_alpha(1, 0, 0.0);
+
+        let options = PngOptions {
+            bit_depth: BitDepth::Eight,
+            alpha: true,
+        };
+        let mut encoded = Vec::new();
+        write_png(&image, &mut encoded, &DisplayTransform::LEGACY, options).unwrap();
+
+        let mut reader = ::png::Decoder::new(encoded.as_slice()).read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        assert_eq!(data, [128, 128, 128, 128, 255, 255, 255, 0]);
+    }
+}
@@ -0,0 +1,198 @@
+use std::io::{self, BufRead, Write};
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::tonemap::{DisplayTransform, TransferFunction};
+
+// Headers asking for more pixels than this are treated as corrupt rather than
+// allocated.
+const MAX_PIXELS: usize = 1 << 26;
+
+pub fn write_p3(image: &Image, out: &mut impl Write, display: &DisplayTransform) -> io::Result<()> {
+    writeln!(out, "P3")?;
+    writeln!(out, "{} {}", image.width(), image.height())?;
+    writeln!(out, "255")?;
+
+    for pixel in image.pixels() {
+        let [r, g, b] = display.apply(*pixel).to_rgb8();
+        writeln!(out, "{} {} {}", r, g, b)?;
+    }
+
+    Ok(())
+}
+
+pub fn write_p6(image: &Image, out: &mut impl Write, display: &DisplayTransform) -> io::Result<()> {
+    writeln!(out, "P6")?;
+    writeln!(out, "{} {}", image.width(), image.height())?;
+    writeln!(out, "255")?;
+
+    let data: Vec<u8> = image
+        .pixels()
+        .iter()
+        .flat_map(|pixel| display.apply(*pixel).to_rgb8())
+        .collect();
+    out.write_all(&data)
+}
+
+pub fn read_ppm(input: &mut impl BufRead) -> io::Result<Image> {
+    let magic = read_token(input)?;
+    let width = parse_token(input)?;
+    let height = parse_token(input)?;
+    let max_value: u32 = parse_token(input)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_data(format!(
+            "invalid PPM max value: {}",
+            max_value
+        )));
+    }
+
+    let pixels = pixel_count(width, height)?;
+    let mut image = Image::new(width, height);
+    let mut samples = Vec::with_capacity(pixels * 3);
+    match magic.as_str() {
+        "P3" => {
+            for _ in 0..pixels * 3 {
+                samples.push(parse_token::<u32>(input)?);
+            }
+        }
+        "P6" => {
+            let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+            let mut data = vec![0; pixels * 3 * bytes_per_sample];
+            input.read_exact(&mut data)?;
+            samples.extend(data.chunks(bytes_per_sample).map(|bytes| match bytes {
+                [b] => *b as u32,
+                [hi, lo] => u16::from_be_bytes([*hi, *lo]) as u32,
+                _ => unreachable!(),
+            }));
+        }
+        _ => return Err(invalid_data(format!("unsupported PPM magic: {}", magic))),
+    }
+
+    for (i, rgb) in samples.chunks(3).enumerate() {
+        let [r, g, b] = [rgb[0], rgb[1], rgb[2]]
+            .map(|c| TransferFunction::Srgb.decode(c.min(max_value) as f64 / max_value as f64));
+        image[(i % width, i / width)] = Color::new(r, g, b);
+    }
+
+    Ok(image)
+}
+
+pub(crate) fn read_token(input: &mut impl BufRead) -> io::Result<String> {
+    let mut token = String::new();
+    let mut byte = [0];
+    loop {
+        if input.read(&mut byte)? == 0 {
+            break;
+        }
+        match byte[0] {
+            b'#' if token.is_empty() => {
+                let mut comment = Vec::new();
+                input.read_until(b'\n', &mut comment)?;
+            }
+            c if c.is_ascii_whitespace() => {
+                if !token.is_empty() {
+                    break;
+                }
+            }
+            c => token.push(c as char),
+        }
+    }
+
+    if token.is_empty() {
+        Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "unexpected end of header",
+        ))
+    } else {
+        Ok(token)
+    }
+}
+
+pub(crate) fn parse_token<T: std::str::FromStr>(input: &mut impl BufRead) -> io::Result<T> {
+    let token = read_token(input)?;
+    token
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid header value: {}", token)))
+}
+
+pub(crate) fn pixel_count(width: usize, height: usize) -> io::Result<usize> {
+    width
+        .checked_mul(height)
+        .filter(|&pixels| pixels <= MAX_PIXELS)
+        .ok_or_else(|| invalid_data(format!("image too large: {}x{}", width, height)))
+}
+
+pub(crate) fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> Image {
+        let mut image = Image::new(4, 3);
+        for y in 0..3 {
+            for x in 0..4 {
+                image[(x, y)] = Color::new(x as f64 / 3.0, y as f64 / 2.0, 0.25);
+            }
+        }
+        image
+    }
+
+    fn assert_round_trip(image: &Image, decoded: &Image) {
+        assert_eq!(
+            (decoded.width(), decoded.height()),
+            (image.width(), image.height())
+        );
+        for (expected, actual) in image.pixels().iter().zip(decoded.pixels()) {
+            // One 8-bit step in sRGB is at most about 1/78 in linear terms.
+            assert!((*expected - *actual).length() < 0.02);
+        }
+    }
+
+    #[test]
+    fn writes_gamma_encoded_p3() {
+        let mut image = Image::new(2, 1);
+        image[(0, 0)] = Color::new(0.0, 0.25, 1.0);
+        image[(1, 0)] = Color::new(4.0, -1.0, 0.01);
+
+        let mut out = Vec::new();
+        write_p3(&image, &mut out, &DisplayTransform::LEGACY).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "P3\n2 1\n255\n0 128 255\n255 0 25\n"
+        );
+    }
+
+    #[test]
+    fn p6_round_trip() {
+        let image = gradient();
+        let mut data = Vec::new();
+        write_p6(&image, &mut data, &DisplayTransform::default()).unwrap();
+        assert_round_trip(&image, &read_ppm(&mut data.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn p3_round_trip() {
+        let image = gradient();
+        let mut data = Vec::new();
+        write_p3(&image, &mut data, &DisplayTransform::default()).unwrap();
+        assert_round_trip(&image, &read_ppm(&mut data.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn reads_comments_and_16_bit_samples() {
+        let mut data = b"P6\n# comment\n1 1\n65535\n".to_vec();
+        data.extend([0xff, 0xff, 0x00, 0x00, 0xff, 0xff]);
+        let image = read_ppm(&mut data.as_slice()).unwrap();
+        let pixel = image[(0, 0)];
+        assert_eq!([pixel.x(), pixel.y(), pixel.z()], [1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn rejects_oversized_header() {
+        let data = b"P6\n18446744073709551615 18446744073709551615\n255\n".to_vec();
+        assert!(read_ppm(&mut data.as_slice()).is_err());
+    }
+}
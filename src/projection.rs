@@ -0,0 +1,303 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use crate::aperture::Aperture;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
+
+pub trait Projection: Debug + Send + Sync {
+    fn generate_ray(
+        &self,
+        film_x: f64,
+        film_y: f64,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Ray>;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ProjectionKind {
+    #[default]
+    Perspective,
+    Orthographic,
+    Fisheye {
+        fov: f64,
+    },
+    Equirectangular,
+    Realistic,
+}
+
+impl FromStr for ProjectionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perspective" => Ok(ProjectionKind::Perspective),
+            "orthographic" => Ok(ProjectionKind::Orthographic),
+            "fisheye" => Ok(ProjectionKind::Fisheye { fov: 180.0 }),
+            "equirectangular" => Ok(ProjectionKind::Equirectangular),
+            "realistic" => Ok(ProjectionKind::Realistic),
+            _ => Err(format!("unknown projection: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Frame {
+    pub origin: Point3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Frame {
+    pub fn look_at(lookfrom: Point3, lookat: Point3, vup: Vec3) -> Self {
+        let w = (lookfrom - lookat).unit();
+        let u = vup.cross(w).unit();
+        let v = w.cross(u);
+        Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+        }
+    }
+
+    // Maps a camera-space direction (x right, y up, z backwards) to world space.
+    pub fn to_world(&self, x: f64, y: f64, z: f64) -> Vec3 {
+        x * self.u + y * self.v + z * self.w
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Perspective {
+    center: Point3,
+    pixel00_loc: Point3,
+    pixel_delta_u: Vec3,
+    pixel_delta_v: Vec3,
+    defocus_disk_u: Vec3,
+    defocus_disk_v: Vec3,
+    defocus: bool,
+    aperture: Aperture,
+    film_center: (f64, f64),
+    film_half_diagonal: f64,
+}
+
+impl Perspective {
+    pub fn new(
+        frame: Frame,
+        image_width: i32,
+        image_height: i32,
+        vfov: f64,
+        focus_distance: f64,
+        defocus_angle: f64,
+        aperture: Aperture,
+    ) -> Self {
+        let theta = vfov.to_radians();
+        let half_height = (theta / 2.0).tan();
+        let viewport_height = 2.0 * half_height * focus_distance;
+        let viewport_width = viewport_height * (image_width as f64 / image_height as f64);
+
+        let viewport_u = viewport_width * frame.u;
+        let viewport_v = viewport_height * -frame.v;
+
+        let pixel_delta_u = viewport_u / image_width as f64;
+        let pixel_delta_v = viewport_v / image_height as f64;
+
+        let viewport_upper_left =
+            frame.origin - focus_distance * frame.w - viewport_u / 2.0 - viewport_v / 2.0;
+        let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
+
+        let defocus_radius = focus_distance * (defocus_angle / 2.0).to_radians().tan();
+        Self {
+            center: frame.origin,
+            pixel00_loc,
+            pixel_delta_u,
+            pixel_delta_v,
+            defocus_disk_u: defocus_radius * frame.u,
+            defocus_disk_v: defocus_radius * frame.v,
+            defocus: defocus_angle > 0.0,
+            aperture,
+            film_center: (0.5 * image_width as f64, 0.5 * image_height as f64),
+            film_half_diagonal: 0.5 * (image_width as f64).hypot(image_height as f64),
+        }
+    }
+
+    fn defocus_disk_sample(
+        &self,
+        film_x: f64,
+        film_y: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Point3> {
+        let p = self.aperture.sample(sampler);
+        let (cx, cy) = self.film_center;
+        if self.aperture.vignetted(
+            p,
+            (film_x - cx) / self.film_half_diagonal,
+            (cy - film_y) / self.film_half_diagonal,
+        ) {
+            return None;
+        }
+        Some(self.center + self.defocus_disk_u * p.x() + self.defocus_disk_v * p.y())
+    }
+}
+
+impl Projection for Perspective {
+    fn generate_ray(
+        &self,
+        film_x: f64,
+        film_y: f64,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Ray> {
+        let pixel_sample = self.pixel00_loc
+            + (film_x - 0.5) * self.pixel_delta_u
+            + (film_y - 0.5) * self.pixel_delta_v;
+
+        let ray_origin = if self.defocus {
+            self.defocus_disk_sample(film_x, film_y, sampler)?
+        } else {
+            self.center
+        };
+        let ray_direction = pixel_sample - ray_origin;
+
+        Some(Ray::new(ray_origin, ray_direction, time))
+    }
+}
+
+#[derive(Debug)]
+pub struct Orthographic {
+    pixel00_loc: Point3,
+    pixel_delta_u: Vec3,
+    pixel_delta_v: Vec3,
+    direction: Vec3,
+}
+
+impl Orthographic {
+    // The view volume matches the perspective frustum's extent at the focus plane.
+    pub fn new(
+        frame: Frame,
+        image_width: i32,
+        image_height: i32,
+        vfov: f64,
+        focus_distance: f64,
+    ) -> Self {
+        let viewport_height = 2.0 * (vfov.to_radians() / 2.0).tan() * focus_distance;
+        let viewport_width = viewport_height * (image_width as f64 / image_height as f64);
+
+        let viewport_u = viewport_width * frame.u;
+        let viewport_v = viewport_height * -frame.v;
+
+        let pixel_delta_u = viewport_u / image_width as f64;
+        let pixel_delta_v = viewport_v / image_height as f64;
+
+        let viewport_upper_left = frame.origin - viewport_u / 2.0 - viewport_v / 2.0;
+        Self {
+            pixel00_loc: viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v),
+            pixel_delta_u,
+            pixel_delta_v,
+            direction: -frame.w,
+        }
+    }
+}
+
+impl Projection for Orthographic {
+    fn generate_ray(
+        &self,
+        film_x: f64,
+        film_y: f64,
+        time: f64,
+        _: &mut dyn Sampler,
+    ) -> Option<Ray> {
+        let origin = self.pixel00_loc
+            + (film_x - 0.5) * self.pixel_delta_u
+            + (film_y - 0.5) * self.pixel_delta_v;
+        Some(Ray::new(origin, self.direction, time))
+    }
+}
+
+#[derive(Debug)]
+pub struct Fisheye {
+    frame: Frame,
+    image_width: f64,
+    image_height: f64,
+    fov: f64,
+}
+
+impl Fisheye {
+    pub fn new(frame: Frame, image_width: i32, image_height: i32, fov: f64) -> Self {
+        Self {
+            frame,
+            image_width: image_width as f64,
+            image_height: image_height as f64,
+            fov: fov.to_radians(),
+        }
+    }
+}
+
+impl Projection for Fisheye {
+    // Equidistant mapping inscribed in the shorter image side.
+    fn generate_ray(
+        &self,
+        film_x: f64,
+        film_y: f64,
+        time: f64,
+        _: &mut dyn Sampler,
+    ) -> Option<Ray> {
+        let half_size = 0.5 * self.image_width.min(self.image_height);
+        let x = (film_x - 0.5 * self.image_width) / half_size;
+        let y = (0.5 * self.image_height - film_y) / half_size;
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta = r * self.fov / 2.0;
+        let phi = y.atan2(x);
+        let direction = self.frame.to_world(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            -theta.cos(),
+        );
+        Some(Ray::new(self.frame.origin, direction, time))
+    }
+}
+
+#[derive(Debug)]
+pub struct Equirectangular {
+    frame: Frame,
+    image_width: f64,
+    image_height: f64,
+}
+
+impl Equirectangular {
+    pub fn new(frame: Frame, image_width: i32, image_height: i32) -> Self {
+        Self {
+            frame,
+            image_width: image_width as f64,
+            image_height: image_height as f64,
+        }
+    }
+}
+
+impl Projection for Equirectangular {
+    // Longitude spans the image width with the view direction at the centre.
+    fn generate_ray(
+        &self,
+        film_x: f64,
+        film_y: f64,
+        time: f64,
+        _: &mut dyn Sampler,
+    ) -> Option<Ray> {
+        let phi = 2.0 * PI * (film_x / self.image_width - 0.5);
+        let theta = PI * film_y / self.image_height;
+        let direction = self.frame.to_world(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        );
+        Some(Ray::new(self.frame.origin, direction, time))
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
+
+// Parallelogram spanned by `u` and `v` from the corner `q`.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+    material: Option<Arc<dyn Material>>,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, material: Option<Arc<dyn Material>>) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit();
+        Self {
+            q,
+            u,
+            v,
+            w: n / n.dot(n),
+            normal,
+            d: normal.dot(q),
+            area: n.length(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, interval: Interval) -> Option<HitRecord> {
+        let denom = self.normal.dot(r.direction());
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(r.origin())) / denom;
+        if !interval.surrounds(t) {
+            return None;
+        }
+
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        Some(HitRecord::new(p, self.normal, t, r, self.material.clone()))
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        let Some(record) = self.hit(r, Interval::new(0.001, f64::INFINITY)) else {
+            return 0.0;
+        };
+        let distance_squared = record.t * record.t * r.direction().length_squared();
+        let cosine = (r.direction().dot(record.normal) / r.direction().length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (s, t) = sampler.get_2d();
+        self.q + s * self.u + t * self.v - origin
+    }
+
+    fn is_emissive(&self) -> bool {
+        hittable::is_emissive(&self.material)
+    }
+}
+
+// Axis-aligned box with opposite corners `a` and `b`.
+pub fn cuboid(a: Point3, b: Point3, material: Option<Arc<dyn Material>>) -> HittableList {
+    let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+    let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+    let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+    let mut sides = HittableList::default();
+    let faces = [
+        (Point3::new(min.x(), min.y(), max.z()), dx, dy),
+        (Point3::new(max.x(), min.y(), max.z()), -dz, dy),
+        (Point3::new(max.x(), min.y(), min.z()), -dx, dy),
+        (Point3::new(min.x(), min.y(), min.z()), dz, dy),
+        (Point3::new(min.x(), max.y(), max.z()), dx, -dz),
+        (Point3::new(min.x(), min.y(), min.z()), dx, dz),
+    ];
+    for (q, u, v) in faces {
+        sides.add(Box::new(Quad::new(q, u, v, material.clone())));
+    }
+    sides
+}
@@ -0,0 +1,415 @@
+use std::str::FromStr;
+
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON;
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+pub trait Sampler {
+    fn start_pixel_sample(&mut self, x: i32, y: i32, index: u32);
+    fn get_1d(&mut self) -> f64;
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SamplerKind {
+    #[default]
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    pub fn create(self, seed: u64, samples_per_pixel: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(seed, samples_per_pixel)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!("unknown sampler: {}", s)),
+        }
+    }
+}
+
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Pcg64Mcg,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Pcg64Mcg::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, x: i32, y: i32, index: u32) {
+        self.rng = Pcg64Mcg::seed_from_u64(hash(&[self.seed, pixel_key(x, y), index as u64]));
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.gen()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+pub struct StratifiedSampler {
+    seed: u64,
+    x_samples: u32,
+    y_samples: u32,
+    pixel_hash: u64,
+    index: u32,
+    dimension: u64,
+    rng: Pcg64Mcg,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples_per_pixel: u32) -> Self {
+        let x_samples = ((samples_per_pixel as f64).sqrt() as u32).max(1);
+        let y_samples = samples_per_pixel.div_ceil(x_samples).max(1);
+        Self {
+            seed,
+            x_samples,
+            y_samples,
+            pixel_hash: 0,
+            index: 0,
+            dimension: 0,
+            rng: Pcg64Mcg::seed_from_u64(seed),
+        }
+    }
+
+    fn stratum(&mut self, strata: u32) -> u32 {
+        let permutation = hash(&[self.pixel_hash, self.dimension]) as u32;
+        self.dimension += 1;
+        permutation_element(self.index % strata, strata, permutation)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, x: i32, y: i32, index: u32) {
+        self.pixel_hash = hash(&[self.seed, pixel_key(x, y)]);
+        self.index = index;
+        self.dimension = 0;
+        self.rng = Pcg64Mcg::seed_from_u64(hash(&[self.pixel_hash, index as u64]));
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let strata = self.x_samples * self.y_samples;
+        let stratum = self.stratum(strata);
+        let jitter: f64 = self.rng.gen();
+        ((stratum as f64 + jitter) / strata as f64).min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let stratum = self.stratum(self.x_samples * self.y_samples);
+        let (jx, jy): (f64, f64) = self.rng.gen();
+        let sx = (stratum % self.x_samples) as f64;
+        let sy = (stratum / self.x_samples) as f64;
+        (
+            ((sx + jx) / self.x_samples as f64).min(ONE_MINUS_EPSILON),
+            ((sy + jy) / self.y_samples as f64).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
+
+pub struct HaltonSampler {
+    seed: u64,
+    pixel_hash: u64,
+    index: u64,
+    dimension: usize,
+    rng: Pcg64Mcg,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel_hash: 0,
+            index: 0,
+            dimension: 0,
+            rng: Pcg64Mcg::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: i32, y: i32, index: u32) {
+        self.pixel_hash = hash(&[self.seed, pixel_key(x, y)]);
+        self.index = index as u64;
+        self.dimension = 0;
+        self.rng = Pcg64Mcg::seed_from_u64(hash(&[self.pixel_hash, self.index]));
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        // Dimensions past the prime table fall back to independent samples.
+        let Some(&base) = PRIMES.get(self.dimension) else {
+            return self.rng.gen();
+        };
+        let scramble = hash(&[self.pixel_hash, self.dimension as u64]) as u32;
+        self.dimension += 1;
+        owen_scrambled_radical_inverse(base, self.index, scramble)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+pub struct SobolSampler {
+    seed: u64,
+    pixel_hash: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel_hash: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: i32, y: i32, index: u32) {
+        self.pixel_hash = hash(&[self.seed, pixel_key(x, y)]);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.get_2d().0
+    }
+
+    // Padded, shuffled and Owen-scrambled 2D Sobol points (Burley 2020).
+    fn get_2d(&mut self) -> (f64, f64) {
+        let seed = hash(&[self.pixel_hash, self.dimension]) as u32;
+        self.dimension += 1;
+
+        let index = nested_uniform_scramble(self.index, seed);
+        let x = nested_uniform_scramble(sobol(index, 0), hash(&[seed as u64, 0]) as u32);
+        let y = nested_uniform_scramble(sobol(index, 1), hash(&[seed as u64, 1]) as u32);
+        (
+            (x as f64 / 4294967296.0).min(ONE_MINUS_EPSILON),
+            (y as f64 / 4294967296.0).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
+
+fn pixel_key(x: i32, y: i32) -> u64 {
+    ((y as u32 as u64) << 32) | x as u32 as u64
+}
+
+fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x9e3779b97f4a7c15, |h, &v| mix_bits(h ^ mix_bits(v)))
+}
+
+fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5d329728ea185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81dadef4bc2dd44d);
+    v ^= v >> 33;
+    v
+}
+
+// Kensler's hash-based permutation of [0, n).
+fn permutation_element(mut i: u32, n: u32, p: u32) -> u32 {
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % n
+}
+
+fn owen_scrambled_radical_inverse(base: u32, mut a: u64, scramble: u32) -> f64 {
+    let base = base as u64;
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_m = 1.0;
+    let mut reversed_digits = 0u64;
+    while 1.0 - (base - 1) as f64 * inv_base_m < 1.0 {
+        let next = a / base;
+        let digit = a - next * base;
+        let digit_hash = mix_bits(scramble as u64 ^ reversed_digits) as u32;
+        let digit = permutation_element(digit as u32, base as u32, digit_hash) as u64;
+        // Large bases run out of integer digits before float precision.
+        let Some(digits) = reversed_digits
+            .checked_mul(base)
+            .and_then(|shifted| shifted.checked_add(digit))
+        else {
+            break;
+        };
+        reversed_digits = digits;
+        inv_base_m *= inv_base;
+        a = next;
+    }
+    (inv_base_m * reversed_digits as f64).min(ONE_MINUS_EPSILON)
+}
+
+fn sobol(index: u32, dimension: u32) -> u32 {
+    match dimension {
+        0 => index.reverse_bits(),
+        _ => {
+            let mut result = 0;
+            let mut v = 1 << 31;
+            let mut i = index;
+            while i != 0 {
+                if i & 1 != 0 {
+                    result ^= v;
+                }
+                i >>= 1;
+                v ^= v >> 1;
+            }
+            result
+        }
+    }
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Draws `dimensions` 2D points for each of the first `count` samples of a
+    // pixel, grouped by dimension.
+    fn points(sampler: &mut dyn Sampler, count: u32, dimensions: usize) -> Vec<Vec<(f64, f64)>> {
+        let mut points = vec![Vec::new(); dimensions];
+        for index in 0..count {
+            sampler.start_pixel_sample(3, 7, index);
+            for dimension in points.iter_mut() {
+                dimension.push(sampler.get_2d());
+            }
+        }
+        points
+    }
+
+    // Whether every cell of an `nx` by `ny` grid holds the same number of
+    // points.
+    fn stratified(points: &[(f64, f64)], nx: usize, ny: usize) -> bool {
+        let mut counts = vec![0; nx * ny];
+        for &(x, y) in points {
+            assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+            counts[(y * ny as f64) as usize * nx + (x * nx as f64) as usize] += 1;
+        }
+        counts.iter().all(|&count| count == counts[0])
+    }
+
+    #[test]
+    fn samples_depend_only_on_pixel_and_index() {
+        for kind in ["independent", "stratified", "halton", "sobol"] {
+            let kind: SamplerKind = kind.parse().unwrap();
+            let mut a = kind.create(5, 16);
+            let mut b = kind.create(5, 16);
+            b.start_pixel_sample(0, 0, 9);
+            b.get_2d();
+
+            a.start_pixel_sample(4, 2, 3);
+            b.start_pixel_sample(4, 2, 3);
+            for _ in 0..40 {
+                assert_eq!(a.get_1d(), b.get_1d());
+            }
+        }
+    }
+
+    #[test]
+    fn stratified_sampler_fills_every_stratum() {
+        let mut sampler = StratifiedSampler::new(1, 16);
+        let mut strata = [0; 16];
+        let mut cells = Vec::new();
+        for index in 0..16 {
+            sampler.start_pixel_sample(3, 7, index);
+            strata[(sampler.get_1d() * 16.0) as usize] += 1;
+            cells.push(sampler.get_2d());
+        }
+        assert!(strata.iter().all(|&count| count == 1));
+        assert!(stratified(&cells, 4, 4));
+    }
+
+    #[test]
+    fn halton_dimensions_are_stratified_in_their_bases() {
+        // 72 samples are whole blocks in both base 2 and base 3.
+        let mut sampler = HaltonSampler::new(1);
+        let (xs, ys): (Vec<f64>, Vec<f64>) = points(&mut sampler, 72, 1)[0].iter().copied().unzip();
+        let xs: Vec<_> = xs.into_iter().map(|x| (x, 0.0)).collect();
+        let ys: Vec<_> = ys.into_iter().map(|y| (0.0, y)).collect();
+        assert!(stratified(&xs, 8, 1));
+        assert!(stratified(&ys, 1, 9));
+    }
+
+    #[test]
+    fn sobol_points_fill_elementary_intervals() {
+        let mut sampler = SobolSampler::new(1);
+        for dimension in points(&mut sampler, 16, 3) {
+            for (nx, ny) in [(16, 1), (8, 2), (4, 4), (2, 8), (1, 16)] {
+                assert!(stratified(&dimension, nx, ny));
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::vec3::Vec3;
+
+// Luminances are in kcd/m^2 and scaled so that a midday scene exposes near 1.
+const LUMINANCE_SCALE: f64 = 0.05;
+// Solar illuminance outside the atmosphere, in klx.
+const SOLAR_ILLUMINANCE: f64 = 128.0;
+
+// Preetham, Shirley and Smits, "A Practical Analytic Model for Daylight".
+// Directions use +y as the zenith and measure azimuth from -z towards +x.
+#[derive(Clone, Copy, Debug)]
+pub struct Sky {
+    sun_direction: Vec3,
+    zenith: [f64; 3],
+    perez: [[f64; 5]; 3],
+    perez_sun: [f64; 3],
+    sun_irradiance: Color,
+    intensity: f64,
+}
+
+impl Sky {
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64, intensity: f64) -> Self {
+        let elevation = elevation.to_radians();
+        let azimuth = azimuth.to_radians();
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+
+        // The model is only fitted for the sun above the horizon.
+        let theta_sun = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0);
+        let t = turbidity;
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |coefficients: [[f64; 4]; 3]| {
+            let theta = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            let row = |c: [f64; 4]| c.iter().zip(theta).map(|(c, s)| c * s).sum::<f64>();
+            t * t * row(coefficients[0]) + t * row(coefficients[1]) + row(coefficients[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let perez_sun = perez.map(|coefficients| perez_function(coefficients, 0.0, theta_sun));
+        Self {
+            sun_direction,
+            zenith: [zenith_luminance, zenith_x, zenith_y],
+            perez,
+            perez_sun,
+            sun_irradiance: sun_irradiance(elevation, theta_sun, turbidity),
+            intensity,
+        }
+    }
+
+    pub fn evaluate(&self, direction: Vec3) -> Color {
+        let direction = direction.unit();
+        let cos_theta = direction.y().max(1e-3);
+        let gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let [luminance, x, y] = [0, 1, 2].map(|i| {
+            self.zenith[i] * perez_function(self.perez[i], cos_theta.acos(), gamma)
+                / self.perez_sun[i]
+        });
+        self.intensity * LUMINANCE_SCALE * xyy_to_rgb(x, y, luminance.max(0.0))
+    }
+
+    // The sun as a directional light: its direction and the irradiance it
+    // delivers to a surface facing it.
+    pub fn sun(&self) -> Option<(Vec3, Color)> {
+        if self.sun_direction.y() <= 0.0 {
+            return None;
+        }
+        Some((
+            self.sun_direction,
+            self.intensity * LUMINANCE_SCALE * self.sun_irradiance,
+        ))
+    }
+}
+
+fn perez_function([a, b, c, d, e]: [f64; 5], theta: f64, gamma: f64) -> f64 {
+    let cos_gamma = gamma.cos();
+    (1.0 + a * (b / theta.cos().max(1e-3)).exp())
+        * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+// Direct sunlight after Rayleigh and aerosol extinction along the relative
+// air mass, using the optical depths from the same paper at 680, 550 and
+// 440 nm.
+fn sun_irradiance(elevation: f64, theta_sun: f64, turbidity: f64) -> Color {
+    if elevation <= 0.0 {
+        return Color::default();
+    }
+
+    let air_mass = 1.0 / (theta_sun.cos() + 0.15 * (93.885 - theta_sun.to_degrees()).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let transmittance = [0.68, 0.55, 0.44].map(|lambda: f64| {
+        let rayleigh = 0.008735 * lambda.powf(-4.08);
+        let aerosol = beta * lambda.powf(-1.3);
+        (-air_mass * (rayleigh + aerosol)).exp()
+    });
+    SOLAR_ILLUMINANCE * Color::new(transmittance[0], transmittance[1], transmittance[2])
+}
+
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color::default();
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Color::new(
+        3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+    )
+}
@@ -1,30 +1,76 @@
-use std::rc::Rc;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
-use crate::hittable::{HitRecord, Hittable};
+use crate::hittable::{self, HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::vec3::Point3;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
 
 pub struct Sphere {
     center: Point3,
+    motion: Vec3,
+    time0: f64,
+    time1: f64,
     radius: f64,
-    material: Option<Rc<dyn Material>>,
+    material: Option<Arc<dyn Material>>,
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f64, material: Option<Rc<dyn Material>>) -> Self {
+    pub fn new(center: Point3, radius: f64, material: Option<Arc<dyn Material>>) -> Self {
         Self {
             center,
+            motion: Vec3::default(),
+            time0: 0.0,
+            time1: 0.0,
             radius,
             material,
         }
     }
+
+    // Moves from `center0` at `time0` to `center1` at `time1` and rests at
+    // either end outside that range.
+    pub fn moving(
+        center0: Point3,
+        center1: Point3,
+        time0: f64,
+        time1: f64,
+        radius: f64,
+        material: Option<Arc<dyn Material>>,
+    ) -> Self {
+        Self {
+            center: center0,
+            motion: center1 - center0,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    fn center(&self, time: f64) -> Point3 {
+        if self.time1 == self.time0 {
+            return self.center;
+        }
+        let s = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center + s * self.motion
+    }
+
+    fn cos_theta_max(&self, origin: Point3, time: f64) -> Option<f64> {
+        let distance_squared = (self.center(time) - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return None;
+        }
+        Some((1.0 - radius_squared / distance_squared).sqrt())
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, interval: Interval) -> Option<HitRecord> {
-        let oc = r.origin() - self.center;
+        let center = self.center(r.time());
+        let oc = r.origin() - center;
         let a = r.direction().dot(r.direction());
         let half_b = oc.dot(r.direction());
         let c = oc.dot(oc) - self.radius * self.radius;
@@ -43,8 +89,87 @@ impl Hittable for Sphere {
             }
 
             let p = r.at(t);
-            let normal = (p - self.center) / self.radius;
+            let normal = (p - center) / self.radius;
             Some(HitRecord::new(p, normal, t, r, self.material.clone()))
         }
     }
+
+    // Directions are sampled uniformly within the cone the sphere subtends.
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        if self.hit(r, Interval::new(0.001, f64::INFINITY)).is_none() {
+            return 0.0;
+        }
+        let Some(cos_theta_max) = self.cos_theta_max(r.origin(), r.time()) else {
+            return 0.0;
+        };
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let to_center = self.center(time) - origin;
+        let Some(cos_theta_max) = self.cos_theta_max(origin, time) else {
+            return to_center;
+        };
+
+        let (u, v) = sampler.get_2d();
+        let cos_theta = 1.0 + u * (cos_theta_max - 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+
+        let w = to_center.unit();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t = w.cross(a).unit();
+        let s = w.cross(t);
+        sin_theta * phi.cos() * s + sin_theta * phi.sin() * t + cos_theta * w
+    }
+
+    fn is_emissive(&self) -> bool {
+        hittable::is_emissive(&self.material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit_x(sphere: &Sphere, time: f64) -> f64 {
+        let ray = Ray::new(Point3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), time);
+        sphere
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap()
+            .p
+            .x()
+    }
+
+    #[test]
+    fn moving_sphere_rests_outside_its_time_range() {
+        let sphere = Sphere::moving(
+            Point3::default(),
+            Point3::new(2.0, 0.0, 0.0),
+            0.0,
+            1.0,
+            0.5,
+            None,
+        );
+        assert!((hit_x(&sphere, 0.5) - 0.5).abs() < 1e-9);
+        assert!((hit_x(&sphere, 3.0) - 1.5).abs() < 1e-9);
+        assert!((hit_x(&sphere, -1.0) + 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn empty_time_range_is_static() {
+        let sphere = Sphere::moving(
+            Point3::default(),
+            Point3::new(2.0, 0.0, 0.0),
+            1.0,
+            1.0,
+            0.5,
+            None,
+        );
+        assert!((hit_x(&sphere, 5.0) + 0.5).abs() < 1e-9);
+    }
 }
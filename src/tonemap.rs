@@ -0,0 +1,255 @@
+use std::str::FromStr;
+
+use crate::color::Color;
+use crate::interval::Interval;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMap {
+    #[default]
+    Clamp,
+    Reinhard,
+    ExtendedReinhard {
+        white: f64,
+    },
+    Aces,
+    Hable,
+    Agx,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransferFunction {
+    #[default]
+    Srgb,
+    Gamma2,
+    Linear,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DisplayTransform {
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    pub transfer: TransferFunction,
+}
+
+impl DisplayTransform {
+    // Matches the renderer's original output: hard clamp followed by sqrt.
+    pub const LEGACY: DisplayTransform = DisplayTransform {
+        exposure: 0.0,
+        tone_map: ToneMap::Clamp,
+        transfer: TransferFunction::Gamma2,
+    };
+
+    // Writes values in [0, 1] unchanged, for data such as heatmaps.
+    pub const LINEAR: DisplayTransform = DisplayTransform {
+        exposure: 0.0,
+        tone_map: ToneMap::Clamp,
+        transfer: TransferFunction::Linear,
+    };
+
+    pub fn apply(&self, color: Color) -> Color {
+        const UNIT: Interval = Interval::new(0.0, 1.0);
+
+        let exposed = 2f64.powf(self.exposure) * color;
+        let mapped = self.tone_map.apply(exposed);
+        Color::new(
+            self.transfer.encode(UNIT.clamp(mapped.x())),
+            self.transfer.encode(UNIT.clamp(mapped.y())),
+            self.transfer.encode(UNIT.clamp(mapped.z())),
+        )
+    }
+}
+
+impl ToneMap {
+    pub fn apply(&self, color: Color) -> Color {
+        match *self {
+            ToneMap::Clamp => color,
+            ToneMap::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            ToneMap::ExtendedReinhard { white } => {
+                scale_luminance(color, |l| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            ToneMap::Aces => map_channels(color, aces),
+            ToneMap::Hable => {
+                const WHITE: f64 = 11.2;
+                let white_scale = 1.0 / hable(WHITE);
+                map_channels(color, |x| hable(2.0 * x) * white_scale)
+            }
+            ToneMap::Agx => agx(color),
+        }
+    }
+}
+
+impl TransferFunction {
+    pub fn encode(&self, x: f64) -> f64 {
+        match self {
+            TransferFunction::Srgb => {
+                if x <= 0.0031308 {
+                    12.92 * x
+                } else {
+                    1.055 * x.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Gamma2 => x.sqrt(),
+            TransferFunction::Linear => x,
+        }
+    }
+
+    pub fn decode(&self, x: f64) -> f64 {
+        match self {
+            TransferFunction::Srgb => {
+                if x <= 0.04045 {
+                    x / 12.92
+                } else {
+                    ((x + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Gamma2 => x * x,
+            TransferFunction::Linear => x,
+        }
+    }
+}
+
+impl FromStr for ToneMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(ToneMap::Clamp),
+            "reinhard" => Ok(ToneMap::Reinhard),
+            "extended-reinhard" => Ok(ToneMap::ExtendedReinhard { white: 4.0 }),
+            "aces" => Ok(ToneMap::Aces),
+            "hable" => Ok(ToneMap::Hable),
+            "agx" => Ok(ToneMap::Agx),
+            _ => Err(format!("unknown tone map: {}", s)),
+        }
+    }
+}
+
+impl FromStr for TransferFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "srgb" => Ok(TransferFunction::Srgb),
+            "gamma2" => Ok(TransferFunction::Gamma2),
+            "linear" => Ok(TransferFunction::Linear),
+            _ => Err(format!("unknown transfer function: {}", s)),
+        }
+    }
+}
+
+fn map_channels(color: Color, f: impl Fn(f64) -> f64) -> Color {
+    Color::new(f(color.x()), f(color.y()), f(color.z()))
+}
+
+fn scale_luminance(color: Color, f: impl Fn(f64) -> f64) -> Color {
+    let luminance = color.luminance();
+    if luminance <= 0.0 {
+        return Color::default();
+    }
+    f(luminance) / luminance * color
+}
+
+// Narkowicz's fit of the ACES reference rendering transform.
+fn aces(x: f64) -> f64 {
+    let x = 0.6 * x;
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+// John Hable's Uncharted 2 filmic curve.
+fn hable(x: f64) -> f64 {
+    const A: f64 = 0.15;
+    const B: f64 = 0.50;
+    const C: f64 = 0.10;
+    const D: f64 = 0.20;
+    const E: f64 = 0.02;
+    const F: f64 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+// Minimal AgX with the default look, returning linear values.
+fn agx(color: Color) -> Color {
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    let inset = Color::new(
+        0.842479062253094 * color.x()
+            + 0.0784335999999992 * color.y()
+            + 0.0792237451477643 * color.z(),
+        0.0423282422610123 * color.x()
+            + 0.878468636469772 * color.y()
+            + 0.0791661274605434 * color.z(),
+        0.0423756549057051 * color.x() + 0.0784336 * color.y() + 0.879142973793104 * color.z(),
+    );
+
+    let curve = map_channels(inset, |x| {
+        let x = (x.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    });
+
+    let outset = Color::new(
+        1.19687900512017 * curve.x()
+            - 0.0980208811401368 * curve.y()
+            - 0.0990297440797205 * curve.z(),
+        -0.0528968517574562 * curve.x() + 1.15190312990417 * curve.y()
+            - 0.0989611768448433 * curve.z(),
+        -0.0529716355144438 * curve.x() - 0.0980434501171241 * curve.y()
+            + 1.15107367264116 * curve.z(),
+    );
+    map_channels(outset, |x| x.max(0.0).powf(2.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_transform_is_identity_on_unit_range() {
+        for x in [0.0, 0.1, 0.5, 1.0] {
+            let out = DisplayTransform::LINEAR.apply(Color::new(x, x, x));
+            assert_eq!(out.x(), x);
+        }
+        let out = DisplayTransform::LINEAR.apply(Color::new(-1.0, 2.0, 0.5));
+        assert_eq!([out.x(), out.y(), out.z()], [0.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn transfer_functions_round_trip() {
+        for transfer in [
+            TransferFunction::Srgb,
+            TransferFunction::Gamma2,
+            TransferFunction::Linear,
+        ] {
+            assert_eq!(transfer.encode(0.0), 0.0);
+            assert!((transfer.encode(1.0) - 1.0).abs() < 1e-12);
+            for x in [0.001, 0.02, 0.3, 0.9] {
+                assert!((transfer.decode(transfer.encode(x)) - x).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn tone_maps_fix_black_and_stay_monotonic() {
+        for tone_map in [
+            ToneMap::Clamp,
+            ToneMap::Reinhard,
+            ToneMap::ExtendedReinhard { white: 4.0 },
+            ToneMap::Aces,
+            ToneMap::Hable,
+            ToneMap::Agx,
+        ] {
+            assert!(tone_map.apply(Color::default()).luminance().abs() < 1e-12);
+            let mut previous = 0.0;
+            for i in 1..64 {
+                let x = i as f64 / 8.0;
+                let y = tone_map.apply(Color::new(x, x, x)).luminance();
+                assert!(y >= previous);
+                previous = y;
+            }
+        }
+        let white = ToneMap::ExtendedReinhard { white: 4.0 }.apply(Color::new(4.0, 4.0, 4.0));
+        assert!((white.luminance() - 1.0).abs() < 1e-12);
+    }
+}
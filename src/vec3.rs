@@ -1,7 +1,8 @@
+use std::f64::consts::PI;
 use std::fmt::Display;
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
 
-use rand::Rng;
+use crate::sampler::Sampler;
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct Vec3([f64; 3]);
@@ -49,22 +50,16 @@ impl Vec3 {
         self / self.length()
     }
 
-    pub fn unit_random() -> Self {
-        let mut rng = rand::thread_rng();
-        loop {
-            let p = Vec3::new(
-                rng.gen_range(-1.0..=1.0),
-                rng.gen_range(-1.0..=1.0),
-                rng.gen_range(-1.0..=1.0),
-            );
-            if p.length_squared() < 1.0 {
-                return p.unit();
-            }
-        }
+    pub fn unit_random(sampler: &mut dyn Sampler) -> Self {
+        let (u, v) = sampler.get_2d();
+        let z = 1.0 - 2.0 * u;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
     }
 
-    pub fn unit_random_in_hemisphere(normal: Vec3) -> Self {
-        let in_unit_sphere = Vec3::unit_random();
+    pub fn unit_random_in_hemisphere(normal: Vec3, sampler: &mut dyn Sampler) -> Self {
+        let in_unit_sphere = Vec3::unit_random(sampler);
         if in_unit_sphere.dot(normal) > 0.0 {
             in_unit_sphere
         } else {
@@ -72,14 +67,20 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_unit_disk() -> Self {
-        let mut rng = rand::thread_rng();
-        loop {
-            let p = Vec3::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0), 0.0);
-            if p.length_squared() < 1.0 {
-                return p;
-            }
+    pub fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Self {
+        let (u, v) = sampler.get_2d();
+        let ox = 2.0 * u - 1.0;
+        let oy = 2.0 * v - 1.0;
+        if ox == 0.0 && oy == 0.0 {
+            return Vec3::default();
         }
+
+        let (r, theta) = if ox.abs() > oy.abs() {
+            (ox, PI / 4.0 * (oy / ox))
+        } else {
+            (oy, PI / 2.0 - PI / 4.0 * (ox / oy))
+        };
+        Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
     }
 
     pub fn near_zero(self) -> bool {